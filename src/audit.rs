@@ -0,0 +1,141 @@
+// Copyleft (ↄ) meh. <meh@schizofreni.co> | http://meh.schizofreni.co
+//
+// This file is part of screenruster.
+//
+// screenruster is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// screenruster is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::ffi::CString;
+use std::os::unix::fs::OpenOptionsExt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use libc;
+use api::json::{self, JsonValue};
+
+use config::{self, Output};
+
+/// Why the screen was locked.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Lock {
+	/// The session was idle for too long.
+	Idle,
+
+	/// Someone asked for it.
+	Manual,
+
+	/// The system is resuming from suspension.
+	Suspend,
+}
+
+/// Persistent trail of locks, unlocks and failed attempts.
+///
+/// Every entry is a JSON object with the `time` in seconds since the epoch and
+/// the `event`, written as a line to a file or to syslog.
+pub struct Audit {
+	config: config::Audit,
+}
+
+impl Audit {
+	pub fn new(config: config::Audit) -> Audit {
+		Audit {
+			config: config,
+		}
+	}
+
+	/// The screen has been locked.
+	pub fn lock(&self, reason: Lock) {
+		self.record(object!{
+			"time"   => now(),
+			"event"  => "lock",
+			"reason" => match reason {
+				Lock::Idle    => "idle",
+				Lock::Manual  => "manual",
+				Lock::Suspend => "suspend",
+			}
+		});
+	}
+
+	/// An authentication attempt failed, the user is only there if it wasn't
+	/// the owner.
+	pub fn failure(&self, user: Option<&str>) {
+		let mut entry = object!{
+			"time"  => now(),
+			"event" => "failure"
+		};
+
+		if let Some(user) = user {
+			entry["user"] = user.into();
+		}
+
+		self.record(entry);
+	}
+
+	/// An authentication attempt didn't complete in time, it counts as a failed
+	/// attempt.
+	pub fn timeout(&self) {
+		self.record(object!{
+			"time"  => now(),
+			"event" => "timeout"
+		});
+	}
+
+	/// The screen is being unlocked by someone other than the owner.
+	pub fn over(&self, user: &str) {
+		self.record(object!{
+			"time"  => now(),
+			"event" => "override",
+			"user"  => user
+		});
+	}
+
+	/// The screen has been unlocked after the given failed attempts.
+	pub fn unlock(&self, failures: u32) {
+		self.record(object!{
+			"time"     => now(),
+			"event"    => "unlock",
+			"failures" => failures
+		});
+	}
+
+	fn record(&self, entry: JsonValue) {
+		let line = json::stringify(entry);
+
+		match self.config.output() {
+			None => (),
+
+			Some(Output::File(path)) => {
+				let result = OpenOptions::new().create(true).append(true).mode(0o600).open(&path)
+					.and_then(|mut file| file.write_all(format!("{}\n", line).as_bytes()));
+
+				if let Err(err) = result {
+					error!("audit: could not write to {}: {}", path.display(), err);
+				}
+			}
+
+			Some(Output::Syslog) => {
+				if let Ok(line) = CString::new(line) {
+					unsafe {
+						libc::syslog(libc::LOG_AUTHPRIV | libc::LOG_NOTICE, b"%s\0".as_ptr() as *const _, line.as_ptr());
+					}
+				}
+			}
+		}
+	}
+}
+
+/// Seconds since the epoch.
+fn now() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
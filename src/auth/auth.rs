@@ -16,14 +16,19 @@
 // along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::thread;
+use std::cmp;
+use std::time::{Instant, Duration};
 use std::ops::Deref;
-use std::sync::mpsc::{Receiver, Sender, SendError, channel};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{Receiver, Sender, SendError, RecvTimeoutError, channel};
 
 use users;
+use users::os::unix::GroupExt;
 
 use error;
-use config;
-use super::Authenticate;
+use config::{self, Policy, Control};
+use secret::Secret;
+use super::{Authenticate, Listen, Conversation, Events};
 
 pub struct Auth {
 	receiver: Receiver<Response>,
@@ -32,51 +37,673 @@ pub struct Auth {
 
 #[derive(Clone, Debug)]
 pub enum Request {
-	Authenticate(String),
+	Authenticate(Secret),
+
+	/// Authenticate as the user the password is prefixed with.
+	Override(Secret),
+
+	/// The answer to a pending prompt.
+	Answer(Secret),
+
+	/// Start the background methods.
+	Start,
+
+	/// Stop the background methods.
+	Stop,
+
+	/// Internal, a response from the background methods started with the
+	/// given id.
+	Background(u64, Response),
+
+	/// Internal, a response from the attempt with the given id.
+	Forward(u64, Response),
+
+	/// Internal, the attempt with the given id is done.
+	Done(u64, bool),
+
+	/// Internal, the handle was dropped.
+	Exit,
 }
 
 #[derive(Clone, Debug)]
 pub enum Response {
 	Success,
-	Failure,
+
+	/// The attempt failed, with the user that was tried if it was someone
+	/// other than the owner.
+	Failure(Option<String>),
+
+	/// The attempt did not complete in time.
+	Timeout,
+
+	/// Further attempts will be refused for the given amount of seconds, sent
+	/// after a failure or alone when an attempt arrives before the delay
+	/// expired.
+	Delay(u64),
+
+	/// The method is asking the user for further input.
+	Prompt {
+		message: String,
+		echo:    bool,
+	},
+
+	/// An informational message from the method.
+	Info(String),
+
+	/// An error message from the method.
+	Error(String),
+
+	/// The attempt was refused for the given reason.
+	Refused(Reason),
+
+	/// The session is being unlocked by the given user instead of its owner,
+	/// always followed by a success.
+	Override(String),
 }
 
-impl Auth {
-	pub fn spawn(config: config::Auth) -> error::Result<Auth> {
-		let     user    = users::get_current_username().ok_or(error::Auth::UnknownUser)?;
-		let mut methods = Vec::new(): Vec<Box<Authenticate>>;
+/// Why an attempt was refused, regardless of the password being right.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Reason {
+	/// The account has expired.
+	AccountExpired,
+
+	/// The password has expired and was not changed.
+	PasswordExpired,
+}
+
+/// State of the methods that has to survive them being recreated after a
+/// timeout.
+#[derive(Default)]
+struct State {
+	#[cfg(feature = "auth-totp")]
+	totp: super::totp::State,
+
+	#[cfg(any(test, feature = "auth-mock"))]
+	mock: super::mock::State,
+}
+
+/// A running authentication attempt.
+struct Attempt {
+	id:       u64,
+	user:     String,
+	deadline: Option<Instant>,
+	answers:  Sender<Secret>,
+}
+
+/// The duration in whole seconds, rounded up.
+fn seconds(duration: Duration) -> u64 {
+	duration.as_secs() + if duration.subsec_nanos() > 0 { 1 } else { 0 }
+}
+
+/// Names of the compiled in authentication methods.
+fn available() -> Vec<&'static str> {
+	let mut names = Vec::new();
+
+	#[cfg(feature = "auth-internal")]
+	names.push("internal");
 
+	#[cfg(feature = "auth-pam")]
+	names.push("pam");
+
+	#[cfg(feature = "auth-totp")]
+	names.push("totp");
+
+	#[cfg(feature = "auth-exec")]
+	names.push("exec");
+
+	#[cfg(feature = "auth-helper")]
+	names.push("helper");
+
+	#[cfg(feature = "auth-mock")]
+	names.push("mock");
+
+	names
+}
+
+/// Create the authentication method with the given name.
+#[allow(unused_variables)]
+fn method(name: &str, config: &config::Auth, display: &str, state: &State) -> error::Result<Box<Authenticate>> {
+	match name {
 		#[cfg(feature = "auth-internal")]
-		methods.push(box super::internal::new(config.get("internal"))?);
+		"internal" =>
+			Ok(box super::internal::new(config.get("internal"))? as Box<Authenticate>),
 
 		#[cfg(feature = "auth-pam")]
-		methods.push(box super::pam::new(config.get("pam"))?);
+		"pam" =>
+			Ok(box super::pam::new(config.get("pam"), display)? as Box<Authenticate>),
+
+		#[cfg(feature = "auth-totp")]
+		"totp" =>
+			Ok(box super::totp::new(config.get("totp"), state.totp.clone())? as Box<Authenticate>),
+
+		#[cfg(feature = "auth-exec")]
+		"exec" =>
+			Ok(box super::exec::new(config.get("exec"))? as Box<Authenticate>),
+
+		#[cfg(feature = "auth-helper")]
+		"helper" =>
+			Ok(box super::helper::new(config.get("helper"), config.get("pam"))? as Box<Authenticate>),
+
+		#[cfg(any(test, feature = "auth-mock"))]
+		"mock" =>
+			Ok(box super::mock::new(config.get("mock"), state.mock.clone())? as Box<Authenticate>),
+
+		_ =>
+			Err(error::Auth::UnknownMethod(name.into()).into())
+	}
+}
+
+/// Names of the compiled in background methods.
+fn background() -> Vec<&'static str> {
+	let mut names = Vec::new();
+
+	#[cfg(feature = "auth-fprint")]
+	names.push("fprint");
+
+	names
+}
+
+/// Check if the policy lets the given method unlock on its own, which is the
+/// only way a background method can be used.
+fn sufficient(policy: &Policy, name: &str) -> bool {
+	match *policy {
+		Policy::Any =>
+			true,
+
+		Policy::All =>
+			false,
+
+		// It has to be sufficient and not come after a method that has to
+		// succeed anyway.
+		Policy::Chain(ref chain) => {
+			for &(ref method, control) in chain {
+				if method == name {
+					return control == Control::Sufficient;
+				}
+
+				if control == Control::Required || control == Control::Requisite {
+					return false;
+				}
+			}
+
+			false
+		}
+	}
+}
+
+/// Create the compiled in background methods allowed by the policy.
+#[allow(unused_mut)]
+fn listeners(config: &config::Auth) -> error::Result<Vec<Box<Listen>>> {
+	let mut listeners = Vec::new(): Vec<Box<Listen>>;
+	let     policy    = config.policy();
+
+	for name in background() {
+		if !sufficient(&policy, name) {
+			continue;
+		}
+
+		match name {
+			#[cfg(feature = "auth-fprint")]
+			"fprint" =>
+				listeners.push(box super::fprint::new(config.get("fprint"))?),
+
+			_ =>
+				unreachable!()
+		}
+	}
+
+	Ok(listeners)
+}
+
+/// Names of the compiled in methods that are only a second factor.
+fn second() -> Vec<&'static str> {
+	let mut names = Vec::new();
+
+	#[cfg(feature = "auth-totp")]
+	names.push("totp");
+
+	names
+}
+
+/// Expand the policy into a chain of method names and control flags.
+fn chain(policy: Policy) -> Vec<(String, Control)> {
+	match policy {
+		// A second factor succeeding on its own would unlock without the
+		// password, so it's only used when asked for explicitly.
+		Policy::Any =>
+			available().into_iter().filter(|name| !second().contains(name))
+				.map(|name| (name.to_string(), Control::Sufficient)).collect(),
+
+		Policy::All =>
+			available().into_iter().map(|name| (name.to_string(), Control::Required)).collect(),
+
+		Policy::Chain(chain) =>
+			chain,
+	}
+}
+
+/// Create the authentication methods following the policy.
+fn methods(config: &config::Auth, display: &str, state: &State) -> error::Result<Vec<(Control, Box<Authenticate>)>> {
+	let mut methods = Vec::new();
+
+	for (name, control) in chain(config.policy()) {
+		// Background methods are started on their own.
+		if background().iter().any(|&n| n == name) {
+			continue;
+		}
+
+		methods.push((control, method(&name, config, display, state)?));
+	}
+
+	Ok(methods)
+}
+
+/// Check if the given user is allowed to unlock someone else's session.
+fn allowed(config: &config::Auth, user: &str) -> bool {
+	if config.allow_users().iter().any(|name| name == user) {
+		return true;
+	}
+
+	let primary = users::get_user_by_name(user).map(|u| u.primary_group_id());
+
+	config.allow_groups().iter().filter_map(|name| users::get_group_by_name(name)).any(|group|
+		Some(group.gid()) == primary || group.members().iter().any(|name| name == user))
+}
+
+/// Split the typed input in the user to authenticate and the password, allowed
+/// users can unlock the session by typing `user:password` and asking for an
+/// override, otherwise it's the owner's password.
+fn split(config: &config::Auth, owner: &str, mut password: Secret) -> (String, Secret) {
+	let index = password.as_str().find(':');
+
+	if let Some(index) = index {
+		let name = password.as_str()[.. index].to_string();
+
+		if name != owner && allowed(config, &name) {
+			let rest = password.split_off(index + 1);
+			return (name, rest);
+		}
+	}
+
+	(owner.to_string(), password)
+}
+
+/// Run the methods following their control flags.
+///
+/// When `other` is set the user is not the owner of the session, and methods
+/// that can only check the owner fail.
+///
+/// If the attempt fails because of an expired account or password the reason
+/// is returned as an error.
+fn evaluate(methods: &mut [(Control, Box<Authenticate>)], conversation: &mut Conversation, user: &str, other: bool, password: &Secret) -> Result<bool, Reason> {
+	let mut failed  = false;
+	let mut success = false;
+	let mut refused = None;
+
+	// Let methods claim their part of the typed input, what's left is the
+	// password.
+	let mut rest   = password.clone();
+	let mut claims = Vec::new();
+
+	for &mut (_, ref mut method) in methods.iter_mut() {
+		let length = method.claim(rest.as_str());
+
+		claims.push(if length > 0 && length <= rest.len() && rest.as_str().is_char_boundary(rest.len() - length) {
+			let at = rest.len() - length;
+			Some(rest.split_off(at))
+		}
+		else {
+			None
+		});
+	}
+
+	for (&mut (control, ref mut method), claim) in methods.iter_mut().zip(claims.iter()) {
+		let input = claim.as_ref().unwrap_or(&rest);
+
+		// Methods that can only check the owner can't vouch for anyone else.
+		let result = if other && !method.others() {
+			false
+		}
+		else {
+			match method.authenticate(conversation, user, input) {
+				Ok(value) =>
+					value,
+
+				Err(error::Error::Auth(error::Auth::AccountExpired)) => {
+					refused = Some(Reason::AccountExpired);
+					false
+				}
+
+				Err(error::Error::Auth(error::Auth::PasswordExpired)) => {
+					refused = Some(Reason::PasswordExpired);
+					false
+				}
+
+				Err(..) =>
+					false,
+			}
+		};
+
+		match (control, result) {
+			(Control::Sufficient, true) if !failed =>
+				return Ok(true),
+
+			(Control::Requisite, false) =>
+				return refused.map_or(Ok(false), Err),
+
+			(Control::Required, false) =>
+				failed = true,
+
+			(_, true) =>
+				success = true,
+
+			(_, false) =>
+				(),
+		}
+	}
+
+	if success && !failed {
+		Ok(true)
+	}
+	else {
+		refused.map_or(Ok(false), Err)
+	}
+}
+
+impl Auth {
+	pub fn spawn<S: Into<String>>(config: config::Auth, display: S) -> error::Result<Auth> {
+		let     display = display.into();
+		let     user    = users::get_current_username().ok_or(error::Auth::UnknownUser)?;
+		let     state   = State::default();
+		let     initial = config.snapshot();
+		let mut methods = Arc::new(Mutex::new(methods(&initial, &display, &state)?));
+		let mut listen  = listeners(&config)?;
 
 		let (sender, i_receiver) = channel();
 		let (i_sender, receiver) = channel();
+		let internal             = i_sender.clone();
 
 		thread::spawn(move || {
-			'main: while let Ok(request) = receiver.recv() {
+			// How many consecutive failed attempts there have been.
+			let mut failures = 0;
+
+			// Instant until which attempts are refused.
+			let mut until = None: Option<Instant>;
+
+			// The currently running attempt.
+			let mut attempt = None: Option<Attempt>;
+
+			// The id of the last attempt.
+			let mut id = 0;
+
+			// The id of the running background methods.
+			let mut listening = None: Option<u64>;
+
+			// Count a failed attempt, refusing further attempts for a while.
+			macro_rules! failed {
+				() => ({
+					failures += 1;
+
+					// Lock out after too many failures, otherwise double the delay on
+					// every failure.
+					let delay = if config.attempts().map_or(false, |max| failures >= max) {
+						failures = 0;
+						config.lockout()
+					}
+					else {
+						cmp::min(config.max_delay(),
+							config.delay().saturating_mul(1 << cmp::min(failures - 1, 31)))
+					};
+
+					if delay > 0 {
+						until = Some(Instant::now() + Duration::from_secs(delay as u64));
+						sender.send(Response::Delay(delay as u64)).unwrap();
+					}
+				});
+			}
+
+			loop {
+				// Wait for the next request, or until the running attempt times out.
+				let request = match attempt.as_ref().and_then(|a| a.deadline) {
+					Some(deadline) => {
+						let now = Instant::now();
+
+						if now >= deadline {
+							Err(RecvTimeoutError::Timeout)
+						}
+						else {
+							receiver.recv_timeout(deadline - now)
+						}
+					}
+
+					None =>
+						receiver.recv().map_err(|_| RecvTimeoutError::Disconnected)
+				};
+
+				// Attempts as someone else go through the same checks.
+				let (request, over) = match request {
+					Ok(Request::Override(password)) =>
+						(Ok(Request::Authenticate(password)), true),
+
+					request =>
+						(request, false),
+				};
+
 				match request {
-					Request::Authenticate(password) => {
-						if methods.is_empty() {
-							warn!("no authentication method");
+					Err(RecvTimeoutError::Disconnected) | Ok(Request::Exit) => {
+						break;
+					}
 
-							sender.send(Response::Success).unwrap();
-							continue 'main;
+					// The method is stuck, abandon the attempt and recreate the methods
+					// since the stuck one is still holding them, their state is kept.
+					//
+					// They're created from the settings they were first created from,
+					// so a reload doesn't change them in the middle of a lock.
+					Err(RecvTimeoutError::Timeout) => {
+						warn!("authentication timed out");
+
+						attempt = None;
+						methods = Arc::new(Mutex::new(match self::methods(&initial, &display, &state) {
+							Ok(value) =>
+								value,
+
+							// Never keep waiting on the stuck methods, every attempt fails
+							// instead.
+							Err(err) => {
+								error!("could not recreate authentication methods: {:?}", err);
+								sender.send(Response::Error("Authentication is unavailable.".into())).unwrap();
+
+								Vec::new()
+							}
+						}));
+
+						sender.send(Response::Timeout).unwrap();
+
+						// Timing out counts as a failure, or a hanging method would get
+						// around the delay.
+						failed!();
+					}
+
+					Ok(Request::Authenticate(password)) => {
+						if attempt.is_some() {
+							warn!("authentication requested while another is running");
+							continue;
 						}
 
-						for method in &mut methods {
-							if let Ok(true) = method.authenticate(&user, &password) {
-								sender.send(Response::Success).unwrap();
-								continue 'main;
+						// Refuse the attempt if it arrives before the delay expired.
+						if let Some(at) = until {
+							let now = Instant::now();
+
+							if now < at {
+								sender.send(Response::Delay(seconds(at - now))).unwrap();
+								continue;
 							}
 						}
 
-						sender.send(Response::Failure).unwrap();
+						id += 1;
+
+						let (a_sender, a_receiver) = channel();
+						let methods                = methods.clone();
+						let internal               = internal.clone();
+						let owner                  = user.clone();
+						let id                     = id;
+
+						// An allowed user can unlock the session of someone else.
+						let (user, password) = if over {
+							split(&config, &owner, password)
+						}
+						else {
+							(owner.clone(), password)
+						};
+						let other            = user != owner;
+
+						if other {
+							info!("authentication as {} instead of {}", user, owner);
+						}
+
+						let who = user.clone();
+
+						thread::spawn(move || {
+							let mut conversation = Conversation::new(id, internal.clone(), a_receiver);
+							let mut methods      = methods.lock().unwrap();
+
+							// Never unlock without something having checked the password.
+							let success = if methods.is_empty() {
+								error!("no authentication method");
+								false
+							}
+							else {
+								match evaluate(&mut methods, &mut conversation, &user, other, &password) {
+									Ok(success) =>
+										success,
+
+									Err(reason) => {
+										let _ = internal.send(Request::Forward(id, Response::Refused(reason)));
+										false
+									}
+								}
+							};
+
+							let _ = internal.send(Request::Done(id, success));
+						});
+
+						attempt = Some(Attempt {
+							id:       id,
+							user:     who,
+							deadline: config.timeout().map(|t| Instant::now() + Duration::from_secs(t as u64)),
+							answers:  a_sender,
+						});
+					}
+
+					Ok(Request::Start) => {
+						if listening.is_some() {
+							continue;
+						}
+
+						id += 1;
+						listening = Some(id);
+
+						for listener in &mut listen {
+							if let Err(err) = listener.start(&user, Events::new(id, internal.clone())) {
+								warn!("could not start background authentication: {:?}", err);
+							}
+						}
+					}
+
+					Ok(Request::Stop) => {
+						if listening.take().is_some() {
+							for listener in &mut listen {
+								listener.stop();
+							}
+						}
+					}
+
+					// A success from a background method wins over any running attempt,
+					// they're only started when the policy lets them unlock on their own.
+					Ok(Request::Background(from, response)) => {
+						if listening != Some(from) {
+							continue;
+						}
+
+						if let Response::Success = response {
+							attempt   = None;
+							failures  = 0;
+							until     = None;
+							listening = None;
+
+							for listener in &mut listen {
+								listener.stop();
+							}
+						}
+
+						sender.send(response).unwrap();
+					}
+
+					// Forward the answer and restart the deadline.
+					Ok(Request::Answer(answer)) => {
+						if let Some(attempt) = attempt.as_mut() {
+							attempt.deadline = config.timeout().map(|t| Instant::now() + Duration::from_secs(t as u64));
+							let _ = attempt.answers.send(answer);
+						}
+						else {
+							warn!("answer without a pending prompt");
+						}
+					}
+
+					// Ignore anything coming from abandoned attempts.
+					Ok(Request::Forward(from, response)) => {
+						if let Some(attempt) = attempt.as_mut() {
+							if attempt.id != from {
+								continue;
+							}
+
+							// Restart the deadline, the user has to answer.
+							if let Response::Prompt { .. } = response {
+								attempt.deadline = config.timeout().map(|t| Instant::now() + Duration::from_secs(t as u64));
+							}
+
+							sender.send(response).unwrap();
+						}
+					}
+
+					Ok(Request::Done(from, success)) => {
+						if attempt.as_ref().map_or(true, |a| a.id != from) {
+							continue;
+						}
+
+						let finished = attempt.take().unwrap();
+
+						if success {
+							failures = 0;
+							until    = None;
+
+							if finished.user != user {
+								sender.send(Response::Override(finished.user)).unwrap();
+							}
+
+							sender.send(Response::Success).unwrap();
+							continue;
+						}
+
+						let other = if finished.user != user {
+							warn!("authentication as {} failed", finished.user);
+							Some(finished.user)
+						}
+						else {
+							None
+						};
+
+						sender.send(Response::Failure(other)).unwrap();
+						failed!();
 					}
 				}
 			}
+
+			if listening.is_some() {
+				for listener in &mut listen {
+					listener.stop();
+				}
+			}
 		});
 
 		Ok(Auth {
@@ -85,8 +712,24 @@ impl Auth {
 		})
 	}
 
-	pub fn authenticate<S: Into<String>>(&self, password: S) -> Result<(), SendError<Request>> {
-		self.sender.send(Request::Authenticate(password.into()))
+	pub fn authenticate(&self, password: Secret) -> Result<(), SendError<Request>> {
+		self.sender.send(Request::Authenticate(password))
+	}
+
+	pub fn over(&self, password: Secret) -> Result<(), SendError<Request>> {
+		self.sender.send(Request::Override(password))
+	}
+
+	pub fn answer(&self, answer: Secret) -> Result<(), SendError<Request>> {
+		self.sender.send(Request::Answer(answer))
+	}
+
+	pub fn start(&self) -> Result<(), SendError<Request>> {
+		self.sender.send(Request::Start)
+	}
+
+	pub fn stop(&self) -> Result<(), SendError<Request>> {
+		self.sender.send(Request::Stop)
 	}
 }
 
@@ -97,3 +740,359 @@ impl Deref for Auth {
 		&self.receiver
 	}
 }
+
+// The thread holds a sender for its own channel, so it has to be told to stop.
+impl Drop for Auth {
+	fn drop(&mut self) {
+		let _ = self.sender.send(Request::Exit);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+	use std::sync::{Arc, Mutex};
+	use std::sync::mpsc::channel;
+
+	use toml;
+
+	use error;
+	use config::{self, Control};
+	use secret::Secret;
+	use super::{Auth, Response, Reason, Policy, evaluate, chain};
+	use super::super::{Authenticate, Conversation};
+
+	type Log = Arc<Mutex<Vec<(&'static str, String)>>>;
+
+	/// A method with a fixed result, logging the input it's given.
+	struct Stub {
+		name:   &'static str,
+		result: Result<bool, Reason>,
+		claim:  usize,
+		others: bool,
+		log:    Log,
+	}
+
+	impl Authenticate for Stub {
+		fn claim(&mut self, _password: &str) -> usize {
+			self.claim
+		}
+
+		fn others(&self) -> bool {
+			self.others
+		}
+
+		fn authenticate(&mut self, _conversation: &mut Conversation, _user: &str, password: &Secret) -> error::Result<bool> {
+			self.log.lock().unwrap().push((self.name, password.as_str().into()));
+
+			match self.result {
+				Ok(value) =>
+					Ok(value),
+
+				Err(Reason::AccountExpired) =>
+					Err(error::Auth::AccountExpired.into()),
+
+				Err(Reason::PasswordExpired) =>
+					Err(error::Auth::PasswordExpired.into()),
+			}
+		}
+	}
+
+	fn stub(log: &Log, name: &'static str, result: Result<bool, Reason>) -> Stub {
+		Stub { name: name, result: result, claim: 0, others: true, log: log.clone() }
+	}
+
+	/// Evaluate the chain, returning the result and the methods that ran.
+	fn run(chain: Vec<(Control, Stub)>, other: bool, password: &str) -> (Result<bool, Reason>, Vec<(&'static str, String)>) {
+		let (sender, _requests)  = channel();
+		let (_answers, receiver) = channel();
+		let mut conversation     = Conversation::new(1, sender, receiver);
+
+		let     log    = chain.first().map(|&(_, ref stub)| stub.log.clone()).unwrap_or_default();
+		let mut chain  = chain.into_iter().map(|(control, stub)| (control, box stub as Box<Authenticate>)).collect::<Vec<_>>();
+		let     result = evaluate(&mut chain, &mut conversation, "user", other, &Secret::from(password));
+		let     ran    = log.lock().unwrap().clone();
+
+		(result, ran)
+	}
+
+	fn names(ran: &[(&'static str, String)]) -> Vec<&'static str> {
+		ran.iter().map(|&(name, _)| name).collect()
+	}
+
+	#[test]
+	fn required() {
+		let log = Log::default();
+		let (result, ran) = run(vec![
+			(Control::Required, stub(&log, "a", Ok(true))),
+			(Control::Required, stub(&log, "b", Ok(true)))], false, "");
+		assert_eq!(Ok(true), result);
+		assert_eq!(vec!["a", "b"], names(&ran));
+
+		// A failure still runs the rest of the chain.
+		let log = Log::default();
+		let (result, ran) = run(vec![
+			(Control::Required, stub(&log, "a", Ok(false))),
+			(Control::Required, stub(&log, "b", Ok(true)))], false, "");
+		assert_eq!(Ok(false), result);
+		assert_eq!(vec!["a", "b"], names(&ran));
+	}
+
+	#[test]
+	fn requisite() {
+		let log = Log::default();
+		let (result, ran) = run(vec![
+			(Control::Requisite, stub(&log, "a", Ok(false))),
+			(Control::Sufficient, stub(&log, "b", Ok(true)))], false, "");
+		assert_eq!(Ok(false), result);
+		assert_eq!(vec!["a"], names(&ran));
+
+		let log = Log::default();
+		let (result, ran) = run(vec![
+			(Control::Requisite, stub(&log, "a", Ok(true))),
+			(Control::Required, stub(&log, "b", Ok(true)))], false, "");
+		assert_eq!(Ok(true), result);
+		assert_eq!(vec!["a", "b"], names(&ran));
+	}
+
+	#[test]
+	fn sufficient() {
+		let log = Log::default();
+		let (result, ran) = run(vec![
+			(Control::Sufficient, stub(&log, "a", Ok(true))),
+			(Control::Required, stub(&log, "b", Ok(false)))], false, "");
+		assert_eq!(Ok(true), result);
+		assert_eq!(vec!["a"], names(&ran));
+
+		let log = Log::default();
+		let (result, ran) = run(vec![
+			(Control::Sufficient, stub(&log, "a", Ok(false))),
+			(Control::Required, stub(&log, "b", Ok(true)))], false, "");
+		assert_eq!(Ok(true), result);
+		assert_eq!(vec!["a", "b"], names(&ran));
+
+		// A success after a required failure doesn't win.
+		let log = Log::default();
+		let (result, ran) = run(vec![
+			(Control::Required, stub(&log, "a", Ok(false))),
+			(Control::Sufficient, stub(&log, "b", Ok(true)))], false, "");
+		assert_eq!(Ok(false), result);
+		assert_eq!(vec!["a", "b"], names(&ran));
+
+		let log = Log::default();
+		let (result, _) = run(vec![
+			(Control::Sufficient, stub(&log, "a", Ok(false))),
+			(Control::Sufficient, stub(&log, "b", Ok(false)))], false, "");
+		assert_eq!(Ok(false), result);
+	}
+
+	#[test]
+	fn optional() {
+		let log = Log::default();
+		let (result, _) = run(vec![
+			(Control::Optional, stub(&log, "a", Ok(true)))], false, "");
+		assert_eq!(Ok(true), result);
+
+		let log = Log::default();
+		let (result, _) = run(vec![
+			(Control::Optional, stub(&log, "a", Ok(false))),
+			(Control::Required, stub(&log, "b", Ok(true)))], false, "");
+		assert_eq!(Ok(true), result);
+
+		let log = Log::default();
+		let (result, _) = run(vec![
+			(Control::Optional, stub(&log, "a", Ok(true))),
+			(Control::Required, stub(&log, "b", Ok(false)))], false, "");
+		assert_eq!(Ok(false), result);
+
+		let log = Log::default();
+		let (result, _) = run(vec![
+			(Control::Optional, stub(&log, "a", Ok(false)))], false, "");
+		assert_eq!(Ok(false), result);
+
+		assert_eq!(Ok(false), run(vec![], false, "").0);
+	}
+
+	#[test]
+	fn refused() {
+		let log = Log::default();
+		let (result, ran) = run(vec![
+			(Control::Requisite, stub(&log, "a", Err(Reason::AccountExpired))),
+			(Control::Required, stub(&log, "b", Ok(true)))], false, "");
+		assert_eq!(Err(Reason::AccountExpired), result);
+		assert_eq!(vec!["a"], names(&ran));
+
+		let log = Log::default();
+		let (result, _) = run(vec![
+			(Control::Required, stub(&log, "a", Err(Reason::PasswordExpired))),
+			(Control::Required, stub(&log, "b", Ok(true)))], false, "");
+		assert_eq!(Err(Reason::PasswordExpired), result);
+
+		// A sufficient success still wins over an earlier optional refusal.
+		let log = Log::default();
+		let (result, _) = run(vec![
+			(Control::Optional, stub(&log, "a", Err(Reason::PasswordExpired))),
+			(Control::Sufficient, stub(&log, "b", Ok(true)))], false, "");
+		assert_eq!(Ok(true), result);
+	}
+
+	#[test]
+	fn claims() {
+		let log = Log::default();
+		let mut code = stub(&log, "code", Ok(true));
+		code.claim = 6;
+
+		let (result, ran) = run(vec![
+			(Control::Required, stub(&log, "password", Ok(true))),
+			(Control::Required, code)], false, "hunter2123456");
+		assert_eq!(Ok(true), result);
+		assert_eq!(vec![("password", "hunter2".to_string()), ("code", "123456".to_string())], ran);
+
+		// Claims longer than the input are ignored.
+		let log = Log::default();
+		let mut code = stub(&log, "code", Ok(true));
+		code.claim = 20;
+
+		let (_, ran) = run(vec![
+			(Control::Required, stub(&log, "password", Ok(true))),
+			(Control::Required, code)], false, "hunter2");
+		assert_eq!(vec![("password", "hunter2".to_string()), ("code", "hunter2".to_string())], ran);
+	}
+
+	#[test]
+	fn second() {
+		assert!(!chain(Policy::Any).iter().any(|&(ref name, _)| name == "totp"));
+
+		// With any method being enough a valid code can't make up for a wrong
+		// password.
+		let log   = Log::default();
+		let stubs = chain(Policy::Any).into_iter().map(|(name, control)|
+			(control, stub(&log, if name == "totp" { "code" } else { "password" }, Ok(name == "totp"))))
+			.collect::<Vec<_>>();
+
+		assert_eq!(Ok(false), run(stubs, false, "wrong123456").0);
+
+		// The code has to come with the password when all are required.
+		let log = Log::default();
+		let (result, _) = run(vec![
+			(Control::Required, stub(&log, "password", Ok(false))),
+			(Control::Required, stub(&log, "code", Ok(true)))], false, "wrong123456");
+		assert_eq!(Ok(false), result);
+	}
+
+	#[test]
+	fn others() {
+		let log = Log::default();
+		let mut owner = stub(&log, "owner", Ok(true));
+		owner.others = false;
+
+		// Methods only checking the owner fail without running for others.
+		let (result, ran) = run(vec![
+			(Control::Sufficient, owner),
+			(Control::Required, stub(&log, "any", Ok(false)))], true, "");
+		assert_eq!(Ok(false), result);
+		assert_eq!(vec!["any"], names(&ran));
+	}
+
+	fn spawn(delay: u32, script: &str) -> Auth {
+		let source = format!(r#"
+			[auth]
+			policy  = [{{ method = "mock" }}]
+			delay   = {}
+			timeout = 1
+
+			[auth.mock]
+			passwords = ["hunter2"]
+			script    = [{}]
+		"#, delay, script);
+
+		let config = config::Auth::default();
+		config.load(&toml::Parser::new(&source).parse().unwrap()).unwrap();
+
+		Auth::spawn(config, ":0").unwrap()
+	}
+
+	fn attempt(auth: &Auth, password: &str) -> Response {
+		auth.authenticate(Secret::from(password)).unwrap();
+		auth.recv_timeout(Duration::from_secs(5)).unwrap()
+	}
+
+	#[test]
+	fn success() {
+		let auth = spawn(0, r#"{ result = "password" }"#);
+
+		match attempt(&auth, "hunter2") {
+			Response::Success => (),
+			other => panic!("unexpected {:?}", other),
+		}
+	}
+
+	#[test]
+	fn failure() {
+		let auth = spawn(0, r#"{ result = "password" }"#);
+
+		match attempt(&auth, "hunter3") {
+			Response::Failure(None) => (),
+			other => panic!("unexpected {:?}", other),
+		}
+	}
+
+	#[test]
+	fn timeout() {
+		let auth = spawn(0, r#"{ result = "hang" }"#);
+
+		match attempt(&auth, "hunter2") {
+			Response::Timeout => (),
+			other => panic!("unexpected {:?}", other),
+		}
+	}
+
+	#[test]
+	fn repeated() {
+		let auth = spawn(0, r#"{ result = "hang" }, { result = "failure" }, { result = "password" }"#);
+
+		match attempt(&auth, "hunter2") {
+			Response::Timeout => (),
+			other => panic!("unexpected {:?}", other),
+		}
+
+		// The script goes on even though the hanging method was recreated.
+		match attempt(&auth, "hunter2") {
+			Response::Failure(None) => (),
+			other => panic!("unexpected {:?}", other),
+		}
+
+		match attempt(&auth, "hunter3") {
+			Response::Failure(None) => (),
+			other => panic!("unexpected {:?}", other),
+		}
+
+		match attempt(&auth, "hunter2") {
+			Response::Success => (),
+			other => panic!("unexpected {:?}", other),
+		}
+	}
+
+	#[test]
+	fn delay() {
+		let auth = spawn(10, r#"{ result = "password" }"#);
+
+		match attempt(&auth, "hunter3") {
+			Response::Failure(None) => (),
+			other => panic!("unexpected {:?}", other),
+		}
+
+		match auth.recv_timeout(Duration::from_secs(5)).unwrap() {
+			Response::Delay(10) => (),
+			other => panic!("unexpected {:?}", other),
+		}
+
+		// Attempts before the delay expired are refused without a failure.
+		match attempt(&auth, "hunter2") {
+			Response::Delay(seconds) if seconds > 0 && seconds <= 10 => (),
+			other => panic!("unexpected {:?}", other),
+		}
+
+		assert!(auth.recv_timeout(Duration::from_millis(500)).is_err());
+	}
+}
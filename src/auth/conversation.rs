@@ -0,0 +1,61 @@
+// Copyleft (ↄ) meh. <meh@schizofreni.co> | http://meh.schizofreni.co
+//
+// This file is part of screenruster.
+//
+// screenruster is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// screenruster is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::mpsc::{Receiver, Sender};
+
+use secret::Secret;
+use super::{Request, Response};
+
+/// A conversation with the user while an authentication attempt is running.
+///
+/// Methods use it to ask for further input or to show messages, prompts and
+/// messages are forwarded to the savers and answers are collected by the
+/// locker.
+pub struct Conversation {
+	id:       u64,
+	sender:   Sender<Request>,
+	receiver: Receiver<Secret>,
+}
+
+impl Conversation {
+	pub fn new(id: u64, sender: Sender<Request>, receiver: Receiver<Secret>) -> Conversation {
+		Conversation {
+			id:       id,
+			sender:   sender,
+			receiver: receiver,
+		}
+	}
+
+	/// Ask the user for input, returns `None` if the conversation was aborted.
+	pub fn prompt<S: Into<String>>(&mut self, message: S, echo: bool) -> Option<Secret> {
+		if self.sender.send(Request::Forward(self.id, Response::Prompt { message: message.into(), echo: echo })).is_err() {
+			return None;
+		}
+
+		self.receiver.recv().ok()
+	}
+
+	/// Show an informational message to the user.
+	pub fn info<S: Into<String>>(&mut self, message: S) {
+		let _ = self.sender.send(Request::Forward(self.id, Response::Info(message.into())));
+	}
+
+	/// Show an error message to the user.
+	pub fn error<S: Into<String>>(&mut self, message: S) {
+		let _ = self.sender.send(Request::Forward(self.id, Response::Error(message.into())));
+	}
+}
@@ -0,0 +1,54 @@
+// Copyleft (ↄ) meh. <meh@schizofreni.co> | http://meh.schizofreni.co
+//
+// This file is part of screenruster.
+//
+// screenruster is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// screenruster is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::mpsc::Sender;
+
+use super::{Request, Response};
+
+/// Events from a method running in the background.
+///
+/// Successes unlock the screen right away, messages are forwarded to the
+/// savers.
+#[derive(Clone)]
+pub struct Events {
+	id:     u64,
+	sender: Sender<Request>,
+}
+
+impl Events {
+	pub fn new(id: u64, sender: Sender<Request>) -> Events {
+		Events {
+			id:     id,
+			sender: sender,
+		}
+	}
+
+	/// The user has been authenticated.
+	pub fn success(&self) {
+		let _ = self.sender.send(Request::Background(self.id, Response::Success));
+	}
+
+	/// Show an informational message to the user.
+	pub fn info<S: Into<String>>(&self, message: S) {
+		let _ = self.sender.send(Request::Background(self.id, Response::Info(message.into())));
+	}
+
+	/// Show an error message to the user.
+	pub fn error<S: Into<String>>(&self, message: S) {
+		let _ = self.sender.send(Request::Background(self.id, Response::Error(message.into())));
+	}
+}
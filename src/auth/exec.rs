@@ -0,0 +1,115 @@
+// Copyleft (ↄ) meh. <meh@schizofreni.co> | http://meh.schizofreni.co
+//
+// This file is part of screenruster.
+//
+// screenruster is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// screenruster is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::Write;
+use std::thread;
+use std::time::Duration;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::channel;
+
+use toml;
+use libc;
+
+use error;
+use secret::Secret;
+use super::{Authenticate, Conversation};
+
+/// Runs an external program, writing the user and password on its stdin.
+pub struct Auth {
+	command:     Vec<String>,
+	environment: Vec<(String, String)>,
+	timeout:     u64,
+}
+
+pub fn new(config: toml::Table) -> error::Result<Auth> {
+	let command = match config.get("command") {
+		Some(&toml::Value::String(ref value)) =>
+			vec![value.clone()],
+
+		Some(&toml::Value::Array(ref value)) =>
+			value.iter().filter_map(|v| v.as_str()).map(|v| v.to_string()).collect(),
+
+		_ =>
+			Vec::new()
+	};
+
+	if command.is_empty() {
+		return Err(error::auth::Exec::MissingCommand.into());
+	}
+
+	Ok(Auth {
+		command: command,
+
+		environment: config.get("environment").and_then(|v| v.as_table()).map(|t| t.iter()
+			.filter_map(|(k, v)| v.as_str().map(|v| (k.clone(), v.to_string())))
+			.collect()).unwrap_or_default(),
+
+		timeout: config.get("timeout").and_then(|v| v.as_integer()).unwrap_or(10) as u64,
+	})
+}
+
+impl Authenticate for Auth {
+	fn others(&self) -> bool {
+		true
+	}
+
+	fn authenticate(&mut self, _conversation: &mut Conversation, user: &str, password: &Secret) -> error::Result<bool> {
+		let mut command = Command::new(&self.command[0]);
+
+		// Only pass a sane `PATH` and what's explicitly configured.
+		command.args(&self.command[1..])
+			.env_clear()
+			.env("PATH", "/usr/local/bin:/usr/bin:/bin")
+			.stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::null());
+
+		for &(ref name, ref value) in &self.environment {
+			command.env(name, value);
+		}
+
+		let mut child = command.spawn()?;
+
+		{
+			let mut input = child.stdin.take().unwrap();
+			input.write_all(user.as_bytes())?;
+			input.write_all(b"\n")?;
+			input.write_all(password.as_str().as_bytes())?;
+			input.write_all(b"\n")?;
+		}
+
+		let id                 = child.id();
+		let (sender, receiver) = channel();
+
+		thread::spawn(move || {
+			let _ = sender.send(child.wait());
+		});
+
+		match receiver.recv_timeout(Duration::from_secs(self.timeout)) {
+			Ok(status) =>
+				Ok(status?.success()),
+
+			Err(..) => {
+				warn!("exec: {} timed out", self.command[0]);
+
+				unsafe {
+					libc::kill(id as libc::pid_t, libc::SIGKILL);
+				}
+
+				Ok(false)
+			}
+		}
+	}
+}
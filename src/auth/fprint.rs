@@ -0,0 +1,354 @@
+// Copyleft (ↄ) meh. <meh@schizofreni.co> | http://meh.schizofreni.co
+//
+// This file is part of screenruster.
+//
+// screenruster is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// screenruster is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::cmp;
+use std::thread;
+use std::time::Duration;
+use std::sync::mpsc::{Sender, Receiver, TryRecvError, RecvTimeoutError, channel};
+
+use toml;
+use dbus;
+
+use error;
+use super::{Listen, Events};
+
+const NAME:    &'static str = "net.reactivated.Fprint";
+const MANAGER: &'static str = "/net/reactivated/Fprint/Manager";
+const DEVICE:  &'static str = "net.reactivated.Fprint.Device";
+
+/// How many milliseconds to wait for fprintd to answer.
+const TIMEOUT: i32 = 5_000;
+
+/// Fingerprint verification through fprintd, running while the password is
+/// typed.
+pub struct Auth {
+	settings: Settings,
+	stop:     Option<Sender<()>>,
+}
+
+#[derive(Clone, Debug)]
+struct Settings {
+	bus:    dbus::BusType,
+	finger: String,
+
+	/// How many fingerprints that don't match are allowed before giving up
+	/// until the next lock.
+	attempts: u32,
+
+	/// How many seconds to wait after a fingerprint that doesn't match.
+	delay: u64,
+}
+
+pub fn new(config: toml::Table) -> error::Result<Auth> {
+	Ok(Auth {
+		settings: Settings {
+			bus: match config.get("bus").and_then(|v| v.as_str()) {
+				Some("session") =>
+					dbus::BusType::Session,
+
+				_ =>
+					dbus::BusType::System,
+			},
+
+			finger:   config.get("finger").and_then(|v| v.as_str()).unwrap_or("any").into(),
+			attempts: cmp::max(config.get("attempts").and_then(|v| v.as_integer()).unwrap_or(5), 1) as u32,
+			delay:    cmp::max(config.get("delay").and_then(|v| v.as_integer()).unwrap_or(1), 0) as u64,
+		},
+
+		stop: None,
+	})
+}
+
+impl Listen for Auth {
+	fn start(&mut self, user: &str, events: Events) -> error::Result<()> {
+		let (sender, receiver) = channel();
+		let settings           = self.settings.clone();
+		let user               = user.to_string();
+
+		self.stop();
+		self.stop = Some(sender);
+
+		thread::spawn(move || {
+			if let Err(err) = verify(&settings, &user, &events, receiver) {
+				warn!("fprint: {:?}", err);
+			}
+		});
+
+		Ok(())
+	}
+
+	fn stop(&mut self) {
+		if let Some(sender) = self.stop.take() {
+			let _ = sender.send(());
+		}
+	}
+}
+
+/// Call a method on the given device.
+fn call(connection: &dbus::Connection, device: &str, method: &str, argument: Option<&str>) -> error::Result<dbus::Message> {
+	let mut message = dbus::Message::new_method_call(NAME, device, DEVICE, method)?;
+
+	if let Some(argument) = argument {
+		message = message.append1(argument);
+	}
+
+	Ok(connection.send_with_reply_and_block(message, TIMEOUT)?)
+}
+
+/// Claim the default device and verify until a match, an unrecoverable error,
+/// too many fingerprints that don't match or until stopped.
+fn verify(settings: &Settings, user: &str, events: &Events, stop: Receiver<()>) -> error::Result<()> {
+	let finger     = &settings.finger[..];
+	let connection = dbus::Connection::get_private(settings.bus)?;
+	let device     = connection.send_with_reply_and_block(
+		dbus::Message::new_method_call(NAME, MANAGER, "net.reactivated.Fprint.Manager", "GetDefaultDevice")?, TIMEOUT)?
+			.get1::<dbus::Path>().map(|p| (&*p).to_owned()).ok_or(error::auth::Fprint::NoDevice)?;
+
+	call(&connection, &device, "Claim", Some(user))?;
+	connection.add_match(&format!("type='signal',path='{}',interface='{}',member='VerifyStatus'", device, DEVICE))?;
+
+	if let Err(err) = call(&connection, &device, "VerifyStart", Some(finger)) {
+		let _ = call(&connection, &device, "Release", None);
+		return Err(err);
+	}
+
+	let mut failures = 0;
+
+	for item in connection.iter(500) {
+		match stop.try_recv() {
+			Err(TryRecvError::Empty) =>
+				(),
+
+			_ =>
+				break,
+		}
+
+		if let dbus::ConnectionItem::Signal(m) = item {
+			if m.member().map_or(true, |m| &*m != "VerifyStatus") {
+				continue;
+			}
+
+			let (result, done) = m.get2::<&str, bool>();
+			let done           = done.unwrap_or(false);
+
+			match result.unwrap_or("") {
+				"verify-match" => {
+					events.success();
+					break;
+				}
+
+				"verify-no-match" => {
+					failures += 1;
+
+					if failures >= settings.attempts {
+						events.error("Too many unrecognized fingerprints, type the password.");
+						break;
+					}
+
+					events.error("Fingerprint not recognized.");
+
+					// Don't let fingers be tried in a quick succession.
+					match stop.recv_timeout(Duration::from_secs(settings.delay)) {
+						Err(RecvTimeoutError::Timeout) =>
+							(),
+
+						_ =>
+							break,
+					}
+				}
+
+				"verify-retry-scan" =>
+					events.info("Scan your finger again."),
+
+				"verify-swipe-too-short" =>
+					events.info("Swipe was too short, try again."),
+
+				"verify-finger-not-centered" =>
+					events.info("Finger not centered, try again."),
+
+				"verify-remove-and-retry" =>
+					events.info("Remove your finger and try again."),
+
+				"verify-disconnected" => {
+					events.error("The fingerprint reader was disconnected.");
+					break;
+				}
+
+				status => {
+					warn!("fprint: {}", status);
+					break;
+				}
+			}
+
+			// Once the verification is done it has to be restarted to try again.
+			if done {
+				call(&connection, &device, "VerifyStop", None)?;
+				call(&connection, &device, "VerifyStart", Some(finger))?;
+			}
+		}
+	}
+
+	let _ = call(&connection, &device, "VerifyStop", None);
+	let _ = call(&connection, &device, "Release", None);
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::env;
+	use std::thread::{self, JoinHandle};
+	use std::time::Duration;
+	use std::io::{BufRead, BufReader};
+	use std::process::{Command, Child, Stdio};
+	use std::sync::mpsc::{Receiver, channel};
+
+	use toml;
+	use dbus;
+
+	use super::{NAME, MANAGER, DEVICE};
+	use super::super::{Listen, Events, Request, Response};
+
+	const PATH: &'static str = "/net/reactivated/Fprint/Device/0";
+
+	/// Start a private bus, returning `None` if `dbus-daemon` isn't available.
+	fn bus() -> Option<Child> {
+		let mut child = if let Ok(child) = Command::new("dbus-daemon")
+			.arg("--session").arg("--nofork").arg("--print-address")
+			.stdout(Stdio::piped()).stderr(Stdio::null()).spawn()
+		{
+			child
+		}
+		else {
+			return None;
+		};
+
+		let mut address = String::new();
+		BufReader::new(child.stdout.as_mut().unwrap()).read_line(&mut address).unwrap();
+		env::set_var("DBUS_SESSION_BUS_ADDRESS", address.trim());
+
+		Some(child)
+	}
+
+	/// Run a fake fprintd answering every verification with the next result,
+	/// until the device is released.
+	fn fprintd(results: Vec<&'static str>) -> JoinHandle<()> {
+		let (sender, receiver) = channel();
+
+		let handle = thread::spawn(move || {
+			let connection = dbus::Connection::get_private(dbus::BusType::Session).unwrap();
+			connection.register_name(NAME, dbus::NameFlag::DoNotQueue as u32).unwrap();
+			connection.register_object_path(MANAGER).unwrap();
+			connection.register_object_path(PATH).unwrap();
+			sender.send(()).unwrap();
+
+			let mut results = results.into_iter();
+
+			for item in connection.iter(100) {
+				if let dbus::ConnectionItem::MethodCall(m) = item {
+					let member = m.member().map(|m| m.to_string()).unwrap_or_default();
+
+					match &*member {
+						"GetDefaultDevice" => {
+							connection.send(m.method_return().append1(dbus::Path::new(PATH).unwrap())).unwrap();
+						}
+
+						"VerifyStart" => {
+							connection.send(m.method_return()).unwrap();
+
+							if let Some(result) = results.next() {
+								connection.send(dbus::Message::new_signal(PATH, DEVICE, "VerifyStatus").unwrap()
+									.append2(result, true)).unwrap();
+							}
+						}
+
+						"Release" => {
+							connection.send(m.method_return()).unwrap();
+							break;
+						}
+
+						_ => {
+							connection.send(m.method_return()).unwrap();
+						}
+					}
+				}
+			}
+		});
+
+		receiver.recv().unwrap();
+		handle
+	}
+
+	fn listen(attempts: u32, results: Vec<&'static str>) -> (super::Auth, Receiver<Request>, JoinHandle<()>) {
+		let fprintd = fprintd(results);
+
+		let mut config = toml::Table::new();
+		config.insert("bus".into(), toml::Value::String("session".into()));
+		config.insert("attempts".into(), toml::Value::Integer(attempts as i64));
+		config.insert("delay".into(), toml::Value::Integer(0));
+
+		let (sender, receiver) = channel();
+		let mut auth           = super::new(config).unwrap();
+		auth.start("user", Events::new(1, sender)).unwrap();
+
+		(auth, receiver, fprintd)
+	}
+
+	fn next(receiver: &Receiver<Request>) -> Option<Response> {
+		match receiver.recv_timeout(Duration::from_secs(5)) {
+			Ok(Request::Background(1, response)) =>
+				Some(response),
+
+			_ =>
+				None,
+		}
+	}
+
+	#[test]
+	fn fprintd_mock() {
+		let mut daemon = if let Some(daemon) = bus() {
+			daemon
+		}
+		else {
+			return;
+		};
+
+		// A match after a miss unlocks.
+		{
+			let (_auth, events, fprintd) = listen(3, vec!["verify-no-match", "verify-match"]);
+
+			assert!(match next(&events) { Some(Response::Error(..)) => true, _ => false });
+			assert!(match next(&events) { Some(Response::Success) => true, _ => false });
+
+			// The device is released once done.
+			fprintd.join().unwrap();
+		}
+
+		// Too many misses stop the verification before the match.
+		{
+			let (_auth, events, fprintd) = listen(2, vec!["verify-no-match", "verify-no-match", "verify-match"]);
+
+			assert!(match next(&events) { Some(Response::Error(..)) => true, _ => false });
+			assert!(match next(&events) { Some(Response::Error(..)) => true, _ => false });
+			assert!(next(&events).is_none());
+
+			fprintd.join().unwrap();
+		}
+
+		let _ = daemon.kill();
+	}
+}
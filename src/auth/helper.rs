@@ -0,0 +1,178 @@
+// Copyleft (ↄ) meh. <meh@schizofreni.co> | http://meh.schizofreni.co
+//
+// This file is part of screenruster.
+//
+// screenruster is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// screenruster is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::{BufRead, BufReader, Write};
+use std::thread;
+use std::time::Duration;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::channel;
+
+use toml;
+use libc;
+
+use error;
+use secret::Secret;
+use service::valid;
+use super::{Authenticate, Conversation};
+
+/// Delegates the checking to the `screenruster-auth` helper.
+///
+/// A new helper is run for every attempt with the PAM service as argument, the
+/// password is written on its `stdin` and it answers with either `success` or
+/// `failure` on its `stdout`, so no state is kept between attempts.
+pub struct Auth {
+	path:    String,
+	service: String,
+	timeout: u64,
+}
+
+/// Create the helper method, the service defaults to the one used by the PAM
+/// method so both check the same rules.
+pub fn new(config: toml::Table, pam: toml::Table) -> error::Result<Auth> {
+	let service = config.get("service").or_else(|| pam.get("service"))
+		.and_then(|v| v.as_str()).unwrap_or("screenruster");
+
+	if !valid(service) {
+		return Err(error::Error::Config(format!("invalid helper service: {}", service)));
+	}
+
+	Ok(Auth {
+		path:    config.get("path").and_then(|v| v.as_str()).unwrap_or("screenruster-auth").into(),
+		service: service.into(),
+		timeout: config.get("timeout").and_then(|v| v.as_integer()).unwrap_or(10) as u64,
+	})
+}
+
+impl Authenticate for Auth {
+	fn authenticate(&mut self, _conversation: &mut Conversation, _user: &str, password: &Secret) -> error::Result<bool> {
+		let mut child = Command::new(&self.path)
+			.arg(&self.service)
+			.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::null())
+			.spawn()?;
+
+		{
+			let mut input = child.stdin.take().unwrap();
+			input.write_all(password.as_str().as_bytes())?;
+			input.write_all(b"\n")?;
+		}
+
+		let id                 = child.id();
+		let output             = child.stdout.take().unwrap();
+		let (sender, receiver) = channel();
+
+		thread::spawn(move || {
+			let mut line = String::new();
+			let     read = BufReader::new(output).read_line(&mut line);
+
+			let _ = child.wait();
+			let _ = sender.send(read.map(|_| line));
+		});
+
+		match receiver.recv_timeout(Duration::from_secs(self.timeout)) {
+			Ok(line) =>
+				Ok(line?.trim() == "success"),
+
+			Err(..) => {
+				warn!("helper: {} timed out", self.path);
+
+				unsafe {
+					libc::kill(id as libc::pid_t, libc::SIGKILL);
+				}
+
+				Ok(false)
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::env;
+	use std::fs::{self, File};
+	use std::io::Write;
+	use std::path::PathBuf;
+	use std::os::unix::fs::PermissionsExt;
+	use std::sync::mpsc::channel;
+
+	use toml;
+
+	use secret::Secret;
+	use service::valid;
+	use super::super::{Authenticate, Conversation};
+
+	/// Write a mock helper accepting `hunter2` for the `test` service, after
+	/// running the given shell code.
+	fn helper(name: &str, before: &str) -> PathBuf {
+		let path = env::temp_dir().join(format!("screenruster-auth-{}-{}", name, unsafe { ::libc::getpid() }));
+
+		{
+			let mut file = File::create(&path).unwrap();
+			write!(file, "#!/bin/sh\n{}\nread password\n", before).unwrap();
+			write!(file, "if [ \"$1\" = test ] && [ \"$password\" = hunter2 ]; then echo success; else echo failure; fi\n").unwrap();
+		}
+
+		fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+		path
+	}
+
+	fn check(path: &PathBuf, service: &str, timeout: i64, password: &str) -> bool {
+		let mut config = toml::Table::new();
+		config.insert("path".into(), toml::Value::String(path.to_str().unwrap().into()));
+		config.insert("timeout".into(), toml::Value::Integer(timeout));
+
+		let mut pam = toml::Table::new();
+		pam.insert("service".into(), toml::Value::String(service.into()));
+
+		let (sender, _requests)  = channel();
+		let (_secrets, receiver) = channel();
+		let mut conversation     = Conversation::new(1, sender, receiver);
+
+		super::new(config, pam).unwrap().authenticate(&mut conversation, "user", &Secret::from(password)).unwrap()
+	}
+
+	#[test]
+	fn mock() {
+		let path = helper("mock", "");
+
+		assert!(check(&path, "test", 10, "hunter2"));
+		assert!(!check(&path, "test", 10, "hunter3"));
+		assert!(!check(&path, "other", 10, "hunter2"));
+
+		fs::remove_file(&path).unwrap();
+	}
+
+	#[test]
+	fn timeout() {
+		let path = helper("timeout", "sleep 5");
+
+		assert!(!check(&path, "test", 1, "hunter2"));
+
+		fs::remove_file(&path).unwrap();
+	}
+
+	#[test]
+	fn service() {
+		assert!(valid("screenruster"));
+		assert!(valid("system-auth"));
+		assert!(valid("pam_test.1"));
+
+		assert!(!valid(""));
+		assert!(!valid(".."));
+		assert!(!valid("../etc/passwd"));
+		assert!(!valid("with space"));
+	}
+}
@@ -15,26 +15,95 @@
 // You should have received a copy of the GNU General Public License
 // along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::ffi::{CStr, CString};
+
 use toml;
+use rand::{self, Rng};
+use libc::c_char;
 
 use error;
-use super::Authenticate;
+use secret::Secret;
+use super::{Authenticate, Conversation};
+
+#[link(name = "crypt")]
+extern "C" {
+	fn crypt(key: *const c_char, setting: *const c_char) -> *mut c_char;
+}
+
+pub enum Auth {
+	/// A crypt(3) hash of the password.
+	Hash(String),
 
-pub struct Auth {
-	password: String,
+	/// The password in plain text.
+	Plain(Secret),
 }
 
 pub fn new(config: toml::Table) -> error::Result<Auth> {
-	Ok(Auth {
-		password: config.get("password")
-			.and_then(|v| v.as_str())
-			.map(|s| s.to_string())
-			.ok_or(error::auth::Internal::Creation)?,
-	})
+	if let Some(hash) = config.get("hash").and_then(|v| v.as_str()) {
+		// Hashes crypt(3) doesn't support would make every attempt fail.
+		encrypt(&Secret::new(), hash)?;
+
+		return Ok(Auth::Hash(hash.into()));
+	}
+
+	if let Some(password) = config.get("password").and_then(|v| v.as_str()) {
+		warn!("internal: plain text password, use `screenruster hash-password` to generate a hash");
+
+		return Ok(Auth::Plain(password.into()));
+	}
+
+	Err(error::auth::Internal::MissingPassword.into())
+}
+
+/// Hash the password with the given crypt(3) setting.
+///
+/// Note `crypt` uses a static buffer, so this must only be called from one
+/// thread at a time.
+fn encrypt(password: &Secret, setting: &str) -> error::Result<String> {
+	let setting = CString::new(setting)?;
+
+	unsafe {
+		let result = crypt(password.as_ptr(), setting.as_ptr());
+
+		// Failure is either a NULL or an invalid hash starting with `*`.
+		if result.is_null() || *result == b'*' as c_char {
+			return Err(error::auth::Internal::InvalidHash.into());
+		}
+
+		Ok(CStr::from_ptr(result).to_string_lossy().into_owned())
+	}
+}
+
+/// Hash the password using SHA-512 with a random salt.
+pub fn hash(password: &Secret) -> error::Result<String> {
+	const SALT: &'static [u8] = b"./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+	let mut rng  = rand::OsRng::new()?;
+	let     salt = (0 .. 16).map(|_| SALT[rng.gen_range(0, SALT.len())] as char).collect::<String>();
+
+	encrypt(password, &format!("$6${}$", salt))
+}
+
+/// Compare in constant time, to avoid leaking how much of the input matched.
+///
+/// The length of a hash only depends on its setting, so it's not compared in
+/// constant time.
+fn equal(a: &[u8], b: &[u8]) -> bool {
+	if a.len() != b.len() {
+		return false;
+	}
+
+	a.iter().zip(b.iter()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
 }
 
 impl Authenticate for Auth {
-	fn authenticate(&mut self, _user: &str, password: &str) -> error::Result<bool> {
-		Ok(self.password == password)
+	fn authenticate(&mut self, _conversation: &mut Conversation, _user: &str, password: &Secret) -> error::Result<bool> {
+		match *self {
+			Auth::Hash(ref hash) =>
+				Ok(equal(encrypt(password, hash)?.as_bytes(), hash.as_bytes())),
+
+			Auth::Plain(ref plain) =>
+				Ok(password.matches(plain)),
+		}
 	}
 }
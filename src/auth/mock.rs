@@ -0,0 +1,173 @@
+// Copyleft (ↄ) meh. <meh@schizofreni.co> | http://meh.schizofreni.co
+//
+// This file is part of screenruster.
+//
+// screenruster is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// screenruster is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::cmp;
+use std::thread;
+use std::time::Duration;
+use std::sync::{Arc, Mutex};
+
+use toml;
+
+use error;
+use secret::Secret;
+use super::{Authenticate, Conversation};
+
+/// A scripted method to exercise the authentication flow without a real
+/// backend.
+///
+/// Every attempt runs the next step of the script, once the script is over the
+/// last step is repeated.
+pub struct Auth {
+	passwords: Vec<String>,
+	script:    Vec<Step>,
+	current:   State,
+}
+
+/// The position in the script, kept when the method is recreated.
+pub type State = Arc<Mutex<usize>>;
+
+/// A step in the script.
+#[derive(Clone, Debug)]
+struct Step {
+	/// How many seconds to wait before answering.
+	delay: u64,
+
+	/// What the attempt results in.
+	result: Outcome,
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+enum Outcome {
+	/// Succeed if the password is one of the accepted ones.
+	Password,
+
+	/// Always succeed.
+	Success,
+
+	/// Always fail.
+	Failure,
+
+	/// Fail with an error.
+	Error,
+
+	/// Fail because the account expired.
+	AccountExpired,
+
+	/// Fail because the password expired.
+	PasswordExpired,
+
+	/// Never answer.
+	Hang,
+}
+
+pub fn new(config: toml::Table, state: State) -> error::Result<Auth> {
+	let passwords = config.get("passwords").and_then(|v| v.as_slice()).map(|array| array.iter()
+		.filter_map(|v| v.as_str())
+		.map(|v| v.to_string())
+		.collect()).unwrap_or_default();
+
+	let mut script = Vec::new();
+
+	for step in config.get("script").and_then(|v| v.as_slice()).unwrap_or(&[]).iter().filter_map(|v| v.as_table()) {
+		script.push(Step {
+			delay: step.get("delay").and_then(|v| v.as_integer()).unwrap_or(0) as u64,
+
+			result: match step.get("result").and_then(|v| v.as_str()).unwrap_or("password") {
+				"password" =>
+					Outcome::Password,
+
+				"success" =>
+					Outcome::Success,
+
+				"failure" =>
+					Outcome::Failure,
+
+				"error" =>
+					Outcome::Error,
+
+				"account-expired" =>
+					Outcome::AccountExpired,
+
+				"password-expired" =>
+					Outcome::PasswordExpired,
+
+				"hang" =>
+					Outcome::Hang,
+
+				other =>
+					return Err(error::auth::Mock::InvalidStep(other.into()).into()),
+			}
+		});
+	}
+
+	if script.is_empty() {
+		script.push(Step { delay: 0, result: Outcome::Password });
+	}
+
+	Ok(Auth {
+		passwords: passwords,
+		script:    script,
+		current:   state,
+	})
+}
+
+impl Authenticate for Auth {
+	fn others(&self) -> bool {
+		true
+	}
+
+	fn authenticate(&mut self, _conversation: &mut Conversation, user: &str, password: &Secret) -> error::Result<bool> {
+		let step = {
+			// The script may have been shortened by a reload.
+			let mut current = self.current.lock().unwrap();
+			let     index   = cmp::min(*current, self.script.len() - 1);
+
+			*current = cmp::min(index + 1, self.script.len() - 1);
+			self.script[index].clone()
+		};
+
+		debug!("mock: {:?} for {}", step, user);
+
+		if step.delay > 0 {
+			thread::sleep(Duration::from_secs(step.delay));
+		}
+
+		match step.result {
+			Outcome::Password =>
+				Ok(self.passwords.iter().any(|p| p == password.as_str())),
+
+			Outcome::Success =>
+				Ok(true),
+
+			Outcome::Failure =>
+				Ok(false),
+
+			Outcome::Error =>
+				Err(error::auth::Mock::Scripted.into()),
+
+			Outcome::AccountExpired =>
+				Err(error::Auth::AccountExpired.into()),
+
+			Outcome::PasswordExpired =>
+				Err(error::Auth::PasswordExpired.into()),
+
+			Outcome::Hang => loop {
+				thread::park();
+			},
+		}
+	}
+}
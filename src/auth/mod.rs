@@ -16,16 +16,61 @@
 // along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
 
 use error;
+use secret::Secret;
 
 pub trait Authenticate: Send + 'static {
-	fn authenticate(&mut self, user: &str, password: &str) -> error::Result<bool>;
+	/// Claim the given amount of trailing bytes of the typed password as input
+	/// for this method, the rest is given to the other methods.
+	fn claim(&mut self, _password: &str) -> usize {
+		0
+	}
+
+	/// Whether the method can check the credentials of users other than the
+	/// owner of the session.
+	fn others(&self) -> bool {
+		false
+	}
+
+	fn authenticate(&mut self, conversation: &mut Conversation, user: &str, password: &Secret) -> error::Result<bool>;
+}
+
+/// A method running in the background while locked, next to the typed
+/// password, like a fingerprint reader.
+pub trait Listen: Send + 'static {
+	/// Start listening for the given user, reporting through the given events
+	/// until stopped.
+	fn start(&mut self, user: &str, events: Events) -> error::Result<()>;
+
+	/// Stop listening.
+	fn stop(&mut self);
 }
 
 mod auth;
-pub use self::auth::{Auth, Request, Response};
+pub use self::auth::{Auth, Request, Response, Reason};
+
+mod conversation;
+pub use self::conversation::Conversation;
+
+mod events;
+pub use self::events::Events;
 
 #[cfg(feature = "auth-internal")]
-mod internal;
+pub mod internal;
 
 #[cfg(feature = "auth-pam")]
 mod pam;
+
+#[cfg(feature = "auth-totp")]
+mod totp;
+
+#[cfg(feature = "auth-exec")]
+mod exec;
+
+#[cfg(feature = "auth-helper")]
+mod helper;
+
+#[cfg(any(test, feature = "auth-mock"))]
+mod mock;
+
+#[cfg(feature = "auth-fprint")]
+mod fprint;
@@ -17,41 +17,102 @@
 
 use std::mem;
 use std::ptr;
+use std::slice;
 use std::ffi::{CStr, CString};
 
 use toml;
 use pam;
 use libc::{c_char, c_int, c_void, size_t};
-use libc::{calloc, free, strdup};
+use libc::{calloc, free, strdup, strlen};
 
 use error;
-use super::Authenticate;
+use secret::{self, Secret};
+use super::{Authenticate, Conversation};
 
 pub struct Auth {
 	accounts: bool,
+	change:   bool,
+	service:  CString,
+	display:  CString,
+	host:     Option<CString>,
 }
 
-pub fn new(_config: toml::Table) -> error::Result<Auth> {
+/// Get the remote host of the display, if it's not a local one.
+///
+/// Displays are in the `[protocol/][host]:display[.screen]` form, IPv6 hosts
+/// can be in brackets, and local displays either have no host, the `unix`
+/// host, a loopback host, or are a path to the socket.
+fn host(display: &str) -> Option<&str> {
+	if display.starts_with('/') {
+		return None;
+	}
+
+	let index = if let Some(index) = display.rfind(':') {
+		index
+	}
+	else {
+		return None;
+	};
+
+	let (protocol, host) = match display[.. index].find('/') {
+		Some(slash) =>
+			(&display[.. slash], &display[slash + 1 .. index]),
+
+		None =>
+			("", &display[.. index]),
+	};
+
+	// DECnet displays use `host::display`.
+	let host = host.trim_right_matches(':').trim_left_matches('[').trim_right_matches(']');
+
+	match (protocol, host) {
+		("unix", _) | ("local", _) | (_, "") | (_, "unix") | (_, "localhost") | (_, "127.0.0.1") | (_, "::1") =>
+			None,
+
+		(_, host) =>
+			Some(host),
+	}
+}
+
+pub fn new(config: toml::Table, display: &str) -> error::Result<Auth> {
+	let host = if let Some(host) = host(display) {
+		Some(CString::new(host)?)
+	}
+	else {
+		None
+	};
+
 	Ok(Auth {
 		accounts: cfg!(feature = "auth-pam-accounts"),
+		change:   config.get("change-password").and_then(|v| v.as_bool()).unwrap_or(false),
+		service:  CString::new(config.get("service").and_then(|v| v.as_str()).unwrap_or("screenruster"))?,
+		display:  CString::new(display)?,
+		host:     host,
 	})
 }
 
-struct Info {
-	user:     *const c_char,
-	password: *const c_char,
+struct Info<'a> {
+	password:     Option<&'a Secret>,
+	conversation: &'a mut Conversation,
 }
 
 impl Authenticate for Auth {
-	fn authenticate(&mut self, user: &str, password: &str) -> error::Result<bool> {
-		let user     = CString::new(user)?;
-		let password = CString::new(password)?;
+	fn others(&self) -> bool {
+		true
+	}
+
+	fn authenticate(&mut self, conversation: &mut Conversation, user: &str, password: &Secret) -> error::Result<bool> {
+		let     user = CString::new(user)?;
+		let mut info = Info {
+			password:     Some(password),
+			conversation: conversation,
+		};
 
 		unsafe {
 			let mut handle = mem::uninitialized();
 			let     conv   = pam::PamConversation {
-				conv:     Some(conversation),
-				data_ptr: &Info { user: user.as_ptr(), password: password.as_ptr() } as *const _ as *mut _,
+				conv:     Some(self::conversation),
+				data_ptr: &mut info as *mut _ as *mut _,
 			};
 
 			macro_rules! pam {
@@ -78,7 +139,7 @@ impl Authenticate for Auth {
 				);
 
 				(start) => (
-					pam!(check pam::start(b"screenruster\x00".as_ptr() as *const _, ptr::null(), &conv, &mut handle))
+					pam!(check pam::start(self.service.as_ptr(), user.as_ptr(), &conv, &mut handle))
 				);
 
 				(set_item $ty:ident => $value:expr) => (
@@ -103,31 +164,82 @@ impl Authenticate for Auth {
 			}
 
 			pam!(start)?;
-			pam!(set_item TTY => b":0.0\0")?;
+			pam!(set_item TTY => self.display)?;
+			pam!(set_item XDISPLAY => self.display)?;
+
+			if let Some(host) = self.host.as_ref() {
+				pam!(set_item RHOST => host)?;
+			}
+
 			pam!(authenticate)?;
 
-			// On some systems account management is not configured properly, but
-			// some PAM modules require it to be called to work properly, so make the
-			// erroring optional.
+			// An expired account or password refuses the unlock even if the
+			// password was right, an expired password can optionally be changed
+			// from the lock screen.
+			match pam::acct_mgmt(handle, pam::PamFlag::NONE) {
+				pam::PamReturnCode::SUCCESS =>
+					(),
+
+				pam::PamReturnCode::ACCT_EXPIRED => {
+					pam::end(handle, pam::PamReturnCode::ACCT_EXPIRED);
+					return Err(error::Auth::AccountExpired.into());
+				}
+
+				pam::PamReturnCode::NEW_AUTHTOK_REQD => {
+					if !self.change {
+						pam::end(handle, pam::PamReturnCode::NEW_AUTHTOK_REQD);
+						return Err(error::Auth::PasswordExpired.into());
+					}
+
+					info.conversation.info("Your password has expired, choose a new one.");
+
+					if pam!(chauthtok CHANGE_EXPIRED_AUTHTOK).is_err() {
+						return Err(error::Auth::PasswordExpired.into());
+					}
+				}
+
+				// On some systems account management is not configured properly,
+				// but some PAM modules require it to be called to work properly, so
+				// make the erroring optional.
+				error => {
+					if self.accounts {
+						pam::end(handle, error);
+						return Err(error::auth::Pam(error).into());
+					}
+				}
+			}
+
 			if self.accounts {
-				pam!(acct_mgmt)?;
 				pam!(setcred REINITIALIZE_CRED)?;
-				pam!(end);
-			}
-			else {
-				if pam!(acct_mgmt).is_ok() {
-					pam!(end);
-				}
 			}
 
+			pam!(end);
+
 			Ok(true)
 		}
 	}
 }
 
+/// Forward a prompt to the user and duplicate the answer for PAM.
+///
+/// PAM takes ownership of the copy and frees it, whether it's wiped before
+/// that is up to the modules, so it may be left behind in freed memory.
+fn answer(conversation: &mut Conversation, message: String, echo: bool) -> Option<*mut c_char> {
+	conversation.prompt(message, echo)
+		.map(|answer| unsafe { strdup(answer.as_ptr()) })
+}
+
+/// Zero and free a response we allocated.
+unsafe fn release(value: *mut c_char) {
+	if !value.is_null() {
+		secret::wipe(slice::from_raw_parts_mut(value as *mut u8, strlen(value) as usize));
+		free(value as *mut _);
+	}
+}
+
 extern "C" fn conversation(count: c_int, messages: *mut *mut pam::PamMessage, responses: *mut *mut pam::PamResponse, data: *mut c_void) -> c_int {
 	unsafe {
-		let     info   = &*(data as *mut Info as *const Info);
+		let     info   = &mut *(data as *mut Info);
 		let mut result = pam::PamReturnCode::SUCCESS;
 
 		*responses = calloc(count as size_t, mem::size_of::<pam::PamResponse>() as size_t).as_mut().unwrap() as *mut _ as *mut _;
@@ -135,33 +247,81 @@ extern "C" fn conversation(count: c_int, messages: *mut *mut pam::PamMessage, re
 		for i in 0 .. count as isize {
 			let message  = &**messages.offset(i);
 			let response = &mut *((*responses).offset(i));
+			let text     = String::from_utf8_lossy(CStr::from_ptr(message.msg).to_bytes()).into_owned();
 
 			match pam::PamMessageStyle::from(message.msg_style) {
-				// Probably the username, since it wants us to echo it.
-				pam::PamMessageStyle::PROMPT_ECHO_ON => {
-					response.resp = strdup(info.user);
+				// The first hidden prompt is for the password that has already been
+				// typed, anything after that is asked to the user.
+				pam::PamMessageStyle::PROMPT_ECHO_OFF => {
+					if let Some(password) = info.password.take() {
+						response.resp = strdup(password.as_ptr());
+					}
+					else if let Some(value) = answer(info.conversation, text, false) {
+						response.resp = value;
+					}
+					else {
+						result = pam::PamReturnCode::CONV_ERR;
+						break;
+					}
 				}
 
-				// Probably the password, since it doesn't want us to echo it.
-				pam::PamMessageStyle::PROMPT_ECHO_OFF => {
-					response.resp = strdup(info.password);
+				pam::PamMessageStyle::PROMPT_ECHO_ON => {
+					if let Some(value) = answer(info.conversation, text, true) {
+						response.resp = value;
+					}
+					else {
+						result = pam::PamReturnCode::CONV_ERR;
+						break;
+					}
 				}
 
 				pam::PamMessageStyle::ERROR_MSG => {
-					result = pam::PamReturnCode::CONV_ERR;
-					error!("{}", String::from_utf8_lossy(CStr::from_ptr(message.msg).to_bytes()));
+					error!("{}", text);
+					info.conversation.error(text);
 				}
 
 				pam::PamMessageStyle::TEXT_INFO => {
-					info!("{}", String::from_utf8_lossy(CStr::from_ptr(message.msg).to_bytes()));
+					info!("{}", text);
+					info.conversation.info(text);
 				}
 			}
 		}
 
 		if result != pam::PamReturnCode::SUCCESS {
+			for i in 0 .. count as isize {
+				release((*((*responses).offset(i))).resp);
+			}
+
 			free(*responses as *mut _);
+			*responses = ptr::null_mut();
 		}
 
 		result as c_int
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::host;
+
+	#[test]
+	fn local() {
+		assert_eq!(host(":0"), None);
+		assert_eq!(host(":0.1"), None);
+		assert_eq!(host("unix:0"), None);
+		assert_eq!(host("unix/:0"), None);
+		assert_eq!(host("localhost:10.0"), None);
+		assert_eq!(host("[::1]:0"), None);
+		assert_eq!(host("/tmp/launch-abc/org.x:0"), None);
+	}
+
+	#[test]
+	fn remote() {
+		assert_eq!(host("example.org:0"), Some("example.org"));
+		assert_eq!(host("example.org:0.1"), Some("example.org"));
+		assert_eq!(host("tcp/example.org:0"), Some("example.org"));
+		assert_eq!(host("[2001:db8::1]:0"), Some("2001:db8::1"));
+		assert_eq!(host("inet6/[2001:db8::1]:0"), Some("2001:db8::1"));
+		assert_eq!(host("decnet::0"), Some("decnet"));
+	}
+}
@@ -0,0 +1,267 @@
+// Copyleft (ↄ) meh. <meh@schizofreni.co> | http://meh.schizofreni.co
+//
+// This file is part of screenruster.
+//
+// screenruster is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// screenruster is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::cmp;
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::os::unix::fs::MetadataExt;
+
+use toml;
+use xdg;
+use users;
+use crypto::hmac::Hmac;
+use crypto::sha1::Sha1;
+use crypto::mac::Mac;
+
+use error;
+use secret::Secret;
+use super::{Authenticate, Conversation};
+
+/// RFC 6238 time based one time passwords.
+pub struct Auth {
+	path:   PathBuf,
+	suffix: bool,
+	digits: u32,
+	step:   u64,
+	skew:   u64,
+
+	// Whether the code was claimed from the typed password.
+	claimed: bool,
+
+	// The last accepted time step, to refuse replays.
+	last: State,
+}
+
+/// The last accepted time step, kept when the method is recreated.
+pub type State = Arc<Mutex<Option<u64>>>;
+
+pub fn new(config: toml::Table, state: State) -> error::Result<Auth> {
+	let path = if let Some(path) = config.get("secret").and_then(|v| v.as_str()) {
+		PathBuf::from(path)
+	}
+	else {
+		xdg::BaseDirectories::with_prefix("screenruster").unwrap()
+			.find_config_file("totp").ok_or(error::auth::Totp::MissingSecret)?
+	};
+
+	let digits = config.get("digits").and_then(|v| v.as_integer()).unwrap_or(6);
+	let step   = config.get("step").and_then(|v| v.as_integer()).unwrap_or(30);
+	let skew   = config.get("skew").and_then(|v| v.as_integer()).unwrap_or(1);
+
+	if digits < 6 || digits > 9 {
+		return Err(error::auth::Totp::InvalidSetting("digits".into()).into());
+	}
+
+	if step < 1 {
+		return Err(error::auth::Totp::InvalidSetting("step".into()).into());
+	}
+
+	if skew < 0 {
+		return Err(error::auth::Totp::InvalidSetting("skew".into()).into());
+	}
+
+	Ok(Auth {
+		path:   path,
+		suffix: config.get("input").and_then(|v| v.as_str()) == Some("suffix"),
+		digits: digits as u32,
+		step:   step as u64,
+		skew:   skew as u64,
+
+		claimed: false,
+		last:    state,
+	})
+}
+
+impl Auth {
+	/// Read the secret, refusing it if anyone else can access the file.
+	fn secret(&self) -> error::Result<Vec<u8>> {
+		let mut file     = File::open(&self.path)?;
+		let     metadata = file.metadata()?;
+
+		if metadata.mode() & 0o077 != 0 || metadata.uid() != users::get_current_uid() {
+			return Err(error::auth::Totp::Permissions.into());
+		}
+
+		let mut content = String::new();
+		file.read_to_string(&mut content)?;
+
+		Ok(decode(content.lines().next().unwrap_or(""))
+			.ok_or(error::auth::Totp::InvalidSecret)?)
+	}
+}
+
+/// Decode a base32 encoded secret, ignoring padding and whitespace.
+fn decode(input: &str) -> Option<Vec<u8>> {
+	let mut result = Vec::new();
+	let mut buffer = 0u64;
+	let mut bits   = 0;
+
+	for ch in input.chars().filter(|&c| !c.is_whitespace() && c != '=') {
+		let value = match ch {
+			'A' ... 'Z' => ch as u64 - 'A' as u64,
+			'a' ... 'z' => ch as u64 - 'a' as u64,
+			'2' ... '7' => ch as u64 - '2' as u64 + 26,
+			_           => return None,
+		};
+
+		buffer  = (buffer << 5) | value;
+		bits   += 5;
+
+		if bits >= 8 {
+			bits -= 8;
+			result.push((buffer >> bits) as u8);
+			buffer &= (1 << bits) - 1;
+		}
+	}
+
+	if result.is_empty() {
+		None
+	}
+	else {
+		Some(result)
+	}
+}
+
+/// Compute the RFC 4226 code for the given counter.
+fn hotp(key: &[u8], counter: u64, digits: u32) -> u32 {
+	let mut mac     = Hmac::new(Sha1::new(), key);
+	let mut message = [0u8; 8];
+
+	for (i, byte) in message.iter_mut().enumerate() {
+		*byte = (counter >> (56 - i * 8)) as u8;
+	}
+
+	mac.input(&message);
+
+	let result = mac.result();
+	let hash   = result.code();
+	let offset = (hash[hash.len() - 1] & 0xf) as usize;
+	let value  =
+		((hash[offset]     as u32 & 0x7f) << 24) |
+		((hash[offset + 1] as u32)        << 16) |
+		((hash[offset + 2] as u32)        << 8)  |
+		 (hash[offset + 3] as u32);
+
+	value % 10u32.pow(digits)
+}
+
+impl Authenticate for Auth {
+	fn claim(&mut self, password: &str) -> usize {
+		let digits = self.digits as usize;
+		let bytes  = password.as_bytes();
+
+		self.claimed = self.suffix && bytes.len() >= digits &&
+			bytes[bytes.len() - digits ..].iter().all(|&b| b >= b'0' && b <= b'9');
+
+		if self.claimed {
+			digits
+		}
+		else {
+			0
+		}
+	}
+
+	fn authenticate(&mut self, conversation: &mut Conversation, _user: &str, password: &Secret) -> error::Result<bool> {
+		// Without a trailing code the whole password would be taken as one.
+		let code = if self.suffix {
+			if !self.claimed {
+				return Ok(false);
+			}
+
+			password.clone()
+		}
+		else if let Some(code) = conversation.prompt("Verification code:", false) {
+			code
+		}
+		else {
+			return Ok(false);
+		};
+
+		let code = if let Ok(code) = code.as_str().trim().parse::<u32>() {
+			code
+		}
+		else {
+			return Ok(false);
+		};
+
+		let key = self.secret()?;
+		let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0) / self.step;
+
+		let mut last = self.last.lock().unwrap();
+
+		// Tolerate clock skew by checking the surrounding time steps.
+		for counter in now.saturating_sub(self.skew) .. now + self.skew + 1 {
+			// Codes that were already used, or older ones, are refused.
+			if last.map_or(false, |last| counter <= last) {
+				continue;
+			}
+
+			if hotp(&key, counter, self.digits) == code {
+				*last = Some(counter);
+				return Ok(true);
+			}
+		}
+
+		Ok(false)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{decode, hotp};
+
+	const KEY: &'static [u8] = b"12345678901234567890";
+
+	#[test]
+	fn base32() {
+		assert_eq!(decode("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ").unwrap(), KEY);
+		assert_eq!(decode("gezd gnbv gy3t qojq gezd gnbv gy3t qojq").unwrap(), KEY);
+		assert_eq!(decode("GEZDGNBV====").unwrap(), b"12345");
+		assert!(decode("GEZDGNB1").is_none());
+		assert!(decode("").is_none());
+	}
+
+	// RFC 4226 appendix D.
+	#[test]
+	fn rfc4226() {
+		let codes = [755224, 287082, 359152, 969429, 338314, 254676, 287922, 162583, 399871, 520489];
+
+		for (counter, &code) in codes.iter().enumerate() {
+			assert_eq!(hotp(KEY, counter as u64, 6), code);
+		}
+	}
+
+	// RFC 6238 appendix B, SHA-1 only.
+	#[test]
+	fn rfc6238() {
+		let codes = [
+			(59,          94287082),
+			(1111111109,  7081804),
+			(1111111111,  14050471),
+			(1234567890,  89005924),
+			(2000000000,  69279037),
+			(20000000000, 65353130),
+		];
+
+		for &(time, code) in &codes {
+			assert_eq!(hotp(KEY, time / 30, 8), code);
+		}
+	}
+}
@@ -0,0 +1,218 @@
+// Copyleft (ↄ) meh. <meh@schizofreni.co> | http://meh.schizofreni.co
+//
+// This file is part of screenruster.
+//
+// screenruster is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// screenruster is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Authorization helper.
+//!
+//! It reads a password from `stdin`, checks it for the user that ran it and
+//! writes `success` or `failure` to `stdout`, this way the daemon doesn't need
+//! to hold any credentials checking code and the helper can be installed with
+//! the privileges needed to do the checking.
+//!
+//! The user is always the real user running the helper, so a setuid helper
+//! can't be used to check other credentials, and the PAM service can be given
+//! as the only argument as long as it's a plain name, it defaults to
+//! `screenruster`.
+
+extern crate libc;
+extern crate users;
+
+#[cfg(feature = "helper-pam")]
+extern crate pam_sys as pam;
+
+use std::env;
+use std::process;
+use std::ffi::CString;
+
+#[path = "../wipe.rs"]
+mod wipe;
+use wipe::wipe;
+
+#[path = "../service.rs"]
+mod service;
+use service::valid;
+
+/// The maximum password length, including the terminating NUL.
+const CAPACITY: usize = 1024;
+
+fn main() {
+	let service = match env::args().nth(1) {
+		Some(ref name) if valid(name) =>
+			CString::new(name.as_str()).unwrap(),
+
+		Some(..) =>
+			process::exit(2),
+
+		None =>
+			CString::new("screenruster").unwrap(),
+	};
+
+	let user = match users::get_user_by_uid(users::get_current_uid()) {
+		Some(user) =>
+			user.name().to_string(),
+
+		None =>
+			process::exit(2),
+	};
+
+	// Read the password straight into locked memory a byte at a time, so no
+	// copies are left behind in buffers we don't control.
+	let mut password = [0u8; CAPACITY];
+	let mut length   = 0;
+
+	unsafe {
+		libc::mlock(password.as_ptr() as *const _, CAPACITY as libc::size_t);
+	}
+
+	loop {
+		let mut byte = 0u8;
+
+		match unsafe { libc::read(libc::STDIN_FILENO, &mut byte as *mut u8 as *mut _, 1) } {
+			1 if byte != b'\n' && byte != 0 => {
+				if length < CAPACITY - 1 {
+					password[length] = byte;
+					length += 1;
+				}
+			}
+
+			0 | 1 =>
+				break,
+
+			_ => {
+				wipe(&mut password);
+				process::exit(2);
+			}
+		}
+	}
+
+	let success = check(&service, &user, &password[.. length + 1]);
+	wipe(&mut password);
+
+	if success {
+		println!("success");
+		process::exit(0);
+	}
+	else {
+		println!("failure");
+		process::exit(1);
+	}
+}
+
+/// Check the NUL terminated password.
+#[cfg(feature = "helper-pam")]
+fn check(service: &CString, user: &str, password: &[u8]) -> bool {
+	use std::mem;
+	use std::slice;
+	use libc::{c_char, c_int, c_void, size_t, calloc, free, strdup, strlen};
+
+	// Only the password is answered, anything else fails the conversation.
+	extern "C" fn conversation(count: c_int, messages: *mut *mut pam::PamMessage, responses: *mut *mut pam::PamResponse, data: *mut c_void) -> c_int {
+		unsafe {
+			let mut result = pam::PamReturnCode::SUCCESS;
+
+			*responses = calloc(count as size_t, mem::size_of::<pam::PamResponse>() as size_t) as *mut _;
+
+			for i in 0 .. count as isize {
+				let message  = &**messages.offset(i);
+				let response = &mut *((*responses).offset(i));
+
+				match pam::PamMessageStyle::from(message.msg_style) {
+					pam::PamMessageStyle::PROMPT_ECHO_OFF => {
+						response.resp = strdup(data as *const c_char);
+					}
+
+					pam::PamMessageStyle::PROMPT_ECHO_ON => {
+						result = pam::PamReturnCode::CONV_ERR;
+					}
+
+					pam::PamMessageStyle::ERROR_MSG |
+					pam::PamMessageStyle::TEXT_INFO => ()
+				}
+			}
+
+			if result != pam::PamReturnCode::SUCCESS {
+				for i in 0 .. count as isize {
+					let value = (*((*responses).offset(i))).resp;
+
+					if !value.is_null() {
+						wipe(slice::from_raw_parts_mut(value as *mut u8, strlen(value) as usize));
+						free(value as *mut _);
+					}
+				}
+
+				free(*responses as *mut _);
+			}
+
+			result as c_int
+		}
+	}
+
+	let user = if let Ok(value) = CString::new(user) { value } else { return false };
+
+	unsafe {
+		let mut handle = mem::uninitialized();
+		let     conv   = pam::PamConversation {
+			conv:     Some(conversation),
+			data_ptr: password.as_ptr() as *mut _,
+		};
+
+		if pam::start(service.as_ptr(), user.as_ptr(), &conv, &mut handle) != pam::PamReturnCode::SUCCESS {
+			return false;
+		}
+
+		let mut result = pam::authenticate(handle, pam::PamFlag::NONE);
+
+		if cfg!(feature = "helper-pam-accounts") && result == pam::PamReturnCode::SUCCESS {
+			result = pam::acct_mgmt(handle, pam::PamFlag::NONE);
+		}
+
+		pam::end(handle, result);
+
+		result == pam::PamReturnCode::SUCCESS
+	}
+}
+
+/// Check the NUL terminated password.
+#[cfg(not(feature = "helper-pam"))]
+fn check(_service: &CString, user: &str, password: &[u8]) -> bool {
+	use std::io::Write;
+	use std::process::{Command, Stdio};
+
+	// Fall back to the PAM shadow helper, which expects a NUL terminated
+	// password on `stdin`.
+	let child = Command::new("unix_chkpwd")
+		.arg(user).arg("nonull")
+		.env_clear().env("PATH", "/sbin:/usr/sbin:/usr/bin:/bin")
+		.stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::null())
+		.spawn();
+
+	if let Ok(mut child) = child {
+		{
+			let input = child.stdin.as_mut().unwrap();
+
+			if input.write_all(password).is_err() {
+				let _ = child.kill();
+				return false;
+			}
+		}
+
+		child.stdin.take();
+		child.wait().map(|s| s.success()).unwrap_or(false)
+	}
+	else {
+		false
+	}
+}
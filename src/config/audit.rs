@@ -0,0 +1,79 @@
+// Copyleft (ↄ) meh. <meh@schizofreni.co> | http://meh.schizofreni.co
+//
+// This file is part of screenruster.
+//
+// screenruster is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// screenruster is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::{Arc, RwLock};
+use std::path::PathBuf;
+
+use toml;
+
+use error;
+use xdg;
+
+#[derive(Clone, Default, Debug)]
+pub struct Audit(pub(super) Arc<RwLock<Data>>);
+
+/// Where the audit trail is written to.
+#[derive(Clone, Debug)]
+pub enum Output {
+	/// Append JSON lines to the file.
+	File(PathBuf),
+
+	/// Send the entries to syslog.
+	Syslog,
+}
+
+#[derive(Debug)]
+pub(super) struct Data {
+	pub output: Option<Output>,
+}
+
+impl Default for Data {
+	fn default() -> Data {
+		Data {
+			output: None,
+		}
+	}
+}
+
+impl Audit {
+	pub fn load(&self, table: &toml::Table) -> error::Result<()> {
+		if let Some(table) = table.get("audit").and_then(|v| v.as_table()) {
+			self.0.write().unwrap().output = match table.get("output") {
+				Some(&toml::Value::String(ref value)) if value == "syslog" =>
+					Some(Output::Syslog),
+
+				Some(&toml::Value::String(ref value)) =>
+					Some(Output::File(value.into())),
+
+				Some(&toml::Value::Boolean(true)) =>
+					Some(Output::File(xdg::BaseDirectories::with_prefix("screenruster")
+						.map_err(|_| error::Error::Config("no data directory for the audit trail".into()))?
+						.place_data_file("audit.log")?)),
+
+				_ =>
+					None
+			};
+		}
+
+		Ok(())
+	}
+
+	/// Where to write the audit trail, `None` if it's disabled.
+	pub fn output(&self) -> Option<Output> {
+		self.0.read().unwrap().output.clone()
+	}
+}
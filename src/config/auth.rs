@@ -19,27 +19,247 @@ use std::sync::{Arc, RwLock};
 
 use toml;
 
+use error;
+
 #[derive(Clone, Default, Debug)]
 pub struct Auth(pub(super) Arc<RwLock<Data>>);
 
-#[derive(Debug)]
+/// How authorization methods are combined.
+#[derive(Clone, Debug)]
+pub enum Policy {
+	/// Any successful method is enough.
+	Any,
+
+	/// Every method has to succeed.
+	All,
+
+	/// Methods are tried in order, each with its own control flag.
+	Chain(Vec<(String, Control)>),
+}
+
+impl Default for Policy {
+	fn default() -> Policy {
+		Policy::Any
+	}
+}
+
+/// How the result of a method in a chain is handled, like PAM control flags.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Control {
+	/// Failure fails the chain, but the rest is still tried.
+	Required,
+
+	/// Failure fails the chain immediately.
+	Requisite,
+
+	/// Success succeeds immediately unless a required method failed before.
+	Sufficient,
+
+	/// The result only matters if no other method succeeded.
+	Optional,
+}
+
+impl Default for Control {
+	fn default() -> Control {
+		Control::Required
+	}
+}
+
+#[derive(Clone, Debug)]
 pub(super) struct Data {
+	pub policy: Policy,
+
+	pub delay:     u32,
+	pub max_delay: u32,
+	pub attempts:  Option<u32>,
+	pub lockout:   u32,
+	pub timeout:   Option<u32>,
+
+	pub allow_users:  Vec<String>,
+	pub allow_groups: Vec<String>,
+
 	pub table: toml::Table,
 }
 
 impl Default for Data {
 	fn default() -> Data {
 		Data {
+			policy: Default::default(),
+
+			delay:     1,
+			max_delay: 30,
+			attempts:  None,
+			lockout:   300,
+			timeout:   Some(30),
+
+			allow_users:  Vec::new(),
+			allow_groups: Vec::new(),
+
 			table: Default::default(),
 		}
 	}
 }
 
 impl Auth {
-	pub fn load(&self, table: &toml::Table) {
+	pub fn load(&self, table: &toml::Table) -> error::Result<()> {
 		if let Some(table) = table.get("auth").and_then(|v| v.as_table()) {
+			match table.get("policy") {
+				Some(&toml::Value::String(ref value)) => {
+					self.0.write().unwrap().policy = match value.as_ref() {
+						"any" =>
+							Policy::Any,
+
+						"all" =>
+							Policy::All,
+
+						_ =>
+							return Err(error::Error::Config(format!("unknown policy `{}`", value)))
+					};
+				}
+
+				Some(&toml::Value::Array(ref value)) => {
+					let mut chain = Vec::new();
+
+					for entry in value {
+						let entry = entry.as_table()
+							.ok_or_else(|| error::Error::Config("policy entries must be tables".into()))?;
+
+						let name = entry.get("method").and_then(|v| v.as_str())
+							.ok_or_else(|| error::Error::Config("policy entry without a method".into()))?;
+
+						chain.push((name.to_string(), match entry.get("control") {
+							None =>
+								Control::Required,
+
+							Some(&toml::Value::String(ref value)) => match value.as_ref() {
+								"required" =>
+									Control::Required,
+
+								"requisite" =>
+									Control::Requisite,
+
+								"sufficient" =>
+									Control::Sufficient,
+
+								"optional" =>
+									Control::Optional,
+
+								_ =>
+									return Err(error::Error::Config(format!("unknown control `{}` for `{}`", value, name)))
+							},
+
+							Some(..) =>
+								return Err(error::Error::Config(format!("invalid control for `{}`", name)))
+						}));
+					}
+
+					// An empty chain would leave nothing to check the password.
+					if chain.is_empty() {
+						return Err(error::Error::Config("empty policy".into()));
+					}
+
+					self.0.write().unwrap().policy = Policy::Chain(chain);
+				}
+
+				Some(..) =>
+					return Err(error::Error::Config("invalid policy".into())),
+
+				None =>
+					()
+			}
+
+			if let Some(value) = super::seconds(table.get("delay")) {
+				self.0.write().unwrap().delay = value;
+			}
+
+			if let Some(value) = super::seconds(table.get("max-delay")) {
+				self.0.write().unwrap().max_delay = value;
+			}
+
+			if let Some(value) = table.get("attempts").and_then(|v| v.as_integer()) {
+				if value < 1 {
+					return Err(error::Error::Config("attempts must be at least 1".into()));
+				}
+
+				self.0.write().unwrap().attempts = Some(value as u32);
+			}
+
+			if let Some(value) = super::seconds(table.get("lockout")) {
+				self.0.write().unwrap().lockout = value;
+			}
+
+			if let Some(value) = super::seconds(table.get("timeout")) {
+				self.0.write().unwrap().timeout = Some(value);
+			}
+
+			if let Some(false) = table.get("timeout").and_then(|v| v.as_bool()) {
+				self.0.write().unwrap().timeout = None;
+			}
+
+			if let Some(array) = table.get("allow-users").and_then(|v| v.as_slice()) {
+				self.0.write().unwrap().allow_users = array.iter()
+					.filter_map(|v| v.as_str())
+					.map(|v| v.to_string())
+					.collect();
+			}
+
+			if let Some(array) = table.get("allow-groups").and_then(|v| v.as_slice()) {
+				self.0.write().unwrap().allow_groups = array.iter()
+					.filter_map(|v| v.as_str())
+					.map(|v| v.to_string())
+					.collect();
+			}
+
 			self.0.write().unwrap().table = table.clone();
 		}
+
+		Ok(())
+	}
+
+	/// How the authorization methods are combined.
+	pub fn policy(&self) -> Policy {
+		self.0.read().unwrap().policy.clone()
+	}
+
+	/// How many seconds to wait after the first failed attempt, doubling on
+	/// every subsequent failure.
+	pub fn delay(&self) -> u32 {
+		self.0.read().unwrap().delay
+	}
+
+	/// The maximum amount of seconds to wait after a failed attempt.
+	pub fn max_delay(&self) -> u32 {
+		self.0.read().unwrap().max_delay
+	}
+
+	/// How many failed attempts are allowed before locking out.
+	pub fn attempts(&self) -> Option<u32> {
+		self.0.read().unwrap().attempts
+	}
+
+	/// How many seconds to lock out for after too many failed attempts.
+	pub fn lockout(&self) -> u32 {
+		self.0.read().unwrap().lockout
+	}
+
+	/// How many seconds an attempt can take before it's abandoned.
+	pub fn timeout(&self) -> Option<u32> {
+		self.0.read().unwrap().timeout
+	}
+
+	/// Users other than the owner allowed to unlock the session.
+	pub fn allow_users(&self) -> Vec<String> {
+		self.0.read().unwrap().allow_users.clone()
+	}
+
+	/// Groups whose members are allowed to unlock the session.
+	pub fn allow_groups(&self) -> Vec<String> {
+		self.0.read().unwrap().allow_groups.clone()
+	}
+
+	/// Copy the current settings, so later reloads don't change them.
+	pub fn snapshot(&self) -> Auth {
+		Auth(Arc::new(RwLock::new(self.0.read().unwrap().clone())))
 	}
 
 	/// Get the configuration for a specific authorization module.
@@ -0,0 +1,155 @@
+// Copyleft (ↄ) meh. <meh@schizofreni.co> | http://meh.schizofreni.co
+//
+// This file is part of screenruster.
+//
+// screenruster is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// screenruster is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
+
+use xkbcommon::xkb;
+use xkbcommon::xkb::keysyms as key;
+
+/// A key symbol together with the modifiers that have to be held.
+#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug)]
+pub struct Binding {
+	pub symbol:  xkb::Keysym,
+	pub control: bool,
+	pub alt:     bool,
+	pub shift:   bool,
+	pub logo:    bool,
+}
+
+impl Binding {
+	/// Create a binding for the given symbol and held modifiers.
+	pub fn new(symbol: xkb::Keysym, control: bool, alt: bool, shift: bool, logo: bool) -> Binding {
+		Binding {
+			symbol:  normalize(symbol),
+			control: control,
+			alt:     alt,
+			shift:   shift,
+			logo:    logo,
+		}
+	}
+
+	/// Parse a binding like `Ctrl+BackSpace` or `Super+l`.
+	pub fn parse<S: AsRef<str>>(value: S) -> Option<Binding> {
+		let mut parts = value.as_ref().split('+').map(|p| p.trim()).collect::<Vec<&str>>();
+		let     name  = if let Some(name) = parts.pop() {
+			name
+		}
+		else {
+			return None;
+		};
+
+		let mut binding = Binding::new(key::KEY_NoSymbol, false, false, false, false);
+
+		for part in parts {
+			match &*part.to_lowercase() {
+				"ctrl" | "control" =>
+					binding.control = true,
+
+				"alt" | "mod1" =>
+					binding.alt = true,
+
+				"shift" =>
+					binding.shift = true,
+
+				"super" | "logo" | "mod4" =>
+					binding.logo = true,
+
+				_ =>
+					return None
+			}
+		}
+
+		let mut symbol = xkb::keysym_from_name(name, xkb::KEYSYM_NO_FLAGS);
+
+		if symbol == key::KEY_NoSymbol {
+			symbol = xkb::keysym_from_name(name, xkb::KEYSYM_CASE_INSENSITIVE);
+		}
+
+		if symbol == key::KEY_NoSymbol {
+			return None;
+		}
+
+		binding.symbol = normalize(symbol);
+
+		Some(binding)
+	}
+
+	/// Check if the binding applies to the pressed keys, the held modifiers
+	/// have to be exactly the ones of the binding, so at most one binding
+	/// applies.
+	pub fn matches(&self, pressed: &Binding) -> bool {
+		self.symbol  == pressed.symbol &&
+		self.control == pressed.control &&
+		self.alt     == pressed.alt &&
+		self.shift   == pressed.shift &&
+		self.logo    == pressed.logo
+	}
+}
+
+/// Lower case latin letters, so bindings don't depend on Shift or Caps Lock.
+fn normalize(symbol: xkb::Keysym) -> xkb::Keysym {
+	if symbol >= key::KEY_A && symbol <= key::KEY_Z {
+		symbol - key::KEY_A + key::KEY_a
+	}
+	else {
+		symbol
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use xkbcommon::xkb::keysyms as key;
+	use super::{Binding, normalize};
+
+	#[test]
+	fn parse() {
+		assert_eq!(Some(Binding::new(key::KEY_BackSpace, true, false, false, false)), Binding::parse("Ctrl+BackSpace"));
+		assert_eq!(Some(Binding::new(key::KEY_l, false, false, false, true)), Binding::parse("Super + l"));
+		assert_eq!(Some(Binding::new(key::KEY_v, true, false, true, false)), Binding::parse("control+SHIFT+v"));
+		assert_eq!(Some(Binding::new(key::KEY_x, false, true, false, true)), Binding::parse("Mod1+Mod4+x"));
+		assert_eq!(Some(Binding::new(key::KEY_Return, false, false, false, false)), Binding::parse("return"));
+
+		assert_eq!(None, Binding::parse(""));
+		assert_eq!(None, Binding::parse("Hyper+a"));
+		assert_eq!(None, Binding::parse("Ctrl+NotAKey"));
+	}
+
+	#[test]
+	fn normalized() {
+		assert_eq!(key::KEY_a, normalize(key::KEY_A));
+		assert_eq!(key::KEY_z, normalize(key::KEY_Z));
+		assert_eq!(key::KEY_a, normalize(key::KEY_a));
+		assert_eq!(key::KEY_Return, normalize(key::KEY_Return));
+
+		assert_eq!(Binding::parse("Ctrl+Shift+v"), Binding::parse("Ctrl+Shift+V"));
+		assert_eq!(Binding::parse("Ctrl+Shift+v").unwrap(), Binding::new(key::KEY_V, true, false, true, false));
+	}
+
+	#[test]
+	fn matches() {
+		let check = Binding::parse("Return").unwrap();
+		let word  = Binding::parse("Ctrl+w").unwrap();
+
+		assert!(check.matches(&Binding::new(key::KEY_Return, false, false, false, false)));
+		assert!(word.matches(&Binding::new(key::KEY_W, true, false, false, false)));
+
+		// Extra or missing modifiers don't match.
+		assert!(!check.matches(&Binding::new(key::KEY_Return, false, false, true, false)));
+		assert!(!check.matches(&Binding::new(key::KEY_Return, true, false, false, false)));
+		assert!(!word.matches(&Binding::new(key::KEY_w, false, false, false, false)));
+		assert!(!word.matches(&Binding::new(key::KEY_w, true, true, false, false)));
+		assert!(!word.matches(&Binding::new(key::KEY_q, true, false, false, false)));
+	}
+}
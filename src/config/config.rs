@@ -15,6 +15,7 @@
 // You should have received a copy of the GNU General Public License
 // along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::mem;
 use std::fs::File;
 use std::path::{Path, PathBuf};
 use std::io::Read;
@@ -24,7 +25,7 @@ use toml;
 use xdg;
 
 use error;
-use super::{Locker, Interface, Timer, Auth, Saver};
+use super::{Locker, Interface, Timer, Auth, Saver, Audit};
 
 #[derive(Clone, Debug, Default)]
 pub struct Config {
@@ -35,6 +36,7 @@ pub struct Config {
 	timer:     Timer,
 	auth:      Auth,
 	saver:     Saver,
+	audit:     Audit,
 }
 
 impl Config {
@@ -45,17 +47,13 @@ impl Config {
 		Ok(config)
 	}
 
-	pub fn reset(&self) {
-		*self.locker.0.write().unwrap()    = Default::default();
-		*self.interface.0.write().unwrap() = Default::default();
-		*self.timer.0.write().unwrap()     = Default::default();
-		*self.auth.0.write().unwrap()      = Default::default();
-		*self.saver.0.write().unwrap()     = Default::default();
-	}
-
+	/// Load the configuration again from the given path, or the last one used,
+	/// every setting not in the file goes back to its default.
+	///
+	/// The file is loaded on its own and only replaces the current settings
+	/// when it's valid, so a broken file leaves everything as it was.
 	pub fn reload<T: AsRef<Path>>(&self, path: Option<T>) -> error::Result<()> {
 		let path = if let Some(path) = path {
-			*self.path.write().unwrap() = Some(path.as_ref().into());
 			path.as_ref().into()
 		}
 		else if let Some(path) = self.path.read().unwrap().clone() {
@@ -66,7 +64,7 @@ impl Config {
 				.place_config_file("config.toml").unwrap()
 		};
 
-		let table = if let Ok(mut file) = File::open(path) {
+		let table = if let Ok(mut file) = File::open(&path) {
 			let mut content = String::new();
 			file.read_to_string(&mut content)?;
 
@@ -76,11 +74,22 @@ impl Config {
 			toml::Table::new()
 		};
 
-		self.locker.load(&table);
-		self.interface.load(&table);
-		self.timer.load(&table);
-		self.auth.load(&table);
-		self.saver.load(&table);
+		let fresh = Config::default();
+		fresh.locker.load(&table)?;
+		fresh.interface.load(&table)?;
+		fresh.timer.load(&table)?;
+		fresh.auth.load(&table)?;
+		fresh.saver.load(&table)?;
+		fresh.audit.load(&table)?;
+
+		*self.path.write().unwrap() = Some(path);
+
+		*self.locker.0.write().unwrap()    = mem::replace(&mut *fresh.locker.0.write().unwrap(), Default::default());
+		*self.interface.0.write().unwrap() = mem::replace(&mut *fresh.interface.0.write().unwrap(), Default::default());
+		*self.timer.0.write().unwrap()     = mem::replace(&mut *fresh.timer.0.write().unwrap(), Default::default());
+		*self.auth.0.write().unwrap()      = mem::replace(&mut *fresh.auth.0.write().unwrap(), Default::default());
+		*self.saver.0.write().unwrap()     = mem::replace(&mut *fresh.saver.0.write().unwrap(), Default::default());
+		*self.audit.0.write().unwrap()     = mem::replace(&mut *fresh.audit.0.write().unwrap(), Default::default());
 
 		Ok(())
 	}
@@ -104,4 +113,39 @@ impl Config {
 	pub fn saver(&self) -> Saver {
 		self.saver.clone()
 	}
+
+	pub fn audit(&self) -> Audit {
+		self.audit.clone()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::env;
+	use std::fs::{self, File};
+	use std::io::Write;
+
+	use super::Config;
+
+	#[test]
+	fn reload() {
+		let path = env::temp_dir().join(format!("screenruster-config-{}.toml", unsafe { ::libc::getpid() }));
+		let save = |content: &str| File::create(&path).unwrap().write_all(content.as_bytes()).unwrap();
+
+		save("[locker]\npassthrough-interval = 300\n");
+		let config = Config::load(Some(&path)).unwrap();
+		assert_eq!(300, config.locker().passthrough_interval());
+
+		// A broken file leaves everything as it was.
+		save("[locker]\npassthrough-interval = -1\n");
+		assert!(config.reload(None: Option<&str>).is_err());
+		assert_eq!(300, config.locker().passthrough_interval());
+
+		// Settings missing from the file go back to their default.
+		save("");
+		config.reload(None: Option<&str>).unwrap();
+		assert_eq!(150, config.locker().passthrough_interval());
+
+		fs::remove_file(&path).unwrap();
+	}
 }
@@ -20,6 +20,8 @@ use std::collections::HashSet;
 
 use toml;
 
+use error;
+
 #[derive(Clone, Default, Debug)]
 pub struct Interface(pub(super) Arc<RwLock<Data>>);
 
@@ -37,7 +39,7 @@ impl Default for Data {
 }
 
 impl Interface {
-	pub fn load(&self, table: &toml::Table) {
+	pub fn load(&self, table: &toml::Table) -> error::Result<()> {
 		if let Some(table) = table.get("interface").and_then(|v| v.as_table()) {
 			if let Some(array) = table.get("ignore").and_then(|v| v.as_slice()) {
 				self.0.write().unwrap().ignore = array.iter()
@@ -46,6 +48,8 @@ impl Interface {
 					.collect();
 			}
 		}
+
+		Ok(())
 	}
 
 	pub fn ignores<T: AsRef<str>>(&self, name: T) -> bool {
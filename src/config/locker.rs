@@ -19,7 +19,9 @@ use std::sync::{Arc, RwLock};
 
 use toml;
 
-use super::OnSuspend;
+use error;
+
+use super::{OnSuspend, Binding};
 
 #[derive(Clone, Default, Debug)]
 pub struct Locker(pub(super) Arc<RwLock<Data>>);
@@ -30,6 +32,33 @@ pub(super) struct Data {
 	pub dpms:    bool,
 
 	pub on_suspend: OnSuspend,
+	pub keys:       Vec<(Binding, Action)>,
+
+	pub passthrough:          Vec<(Binding, String)>,
+	pub passthrough_interval: u64,
+	pub lock_key:             Option<Binding>,
+}
+
+/// What a key does to the typed password.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Action {
+	/// Delete the last character.
+	Delete,
+
+	/// Delete the last word.
+	DeleteWord,
+
+	/// Clear the password.
+	Clear,
+
+	/// Check the password.
+	Check,
+
+	/// Check the password as the user it's prefixed with.
+	Override,
+
+	/// Do nothing, not even insert the key.
+	Ignore,
 }
 
 impl Default for Data {
@@ -39,12 +68,32 @@ impl Default for Data {
 			dpms:    true,
 
 			on_suspend: Default::default(),
+			keys:       [
+				("BackSpace",      Action::Delete),
+				("Ctrl+h",         Action::Delete),
+				("Ctrl+BackSpace", Action::DeleteWord),
+				("Ctrl+w",         Action::DeleteWord),
+				("Escape",         Action::Clear),
+				("Ctrl+u",         Action::Clear),
+				("Return",         Action::Check),
+				("KP_Enter",       Action::Check),
+				("Ctrl+Return",    Action::Override),
+				("Ctrl+KP_Enter",  Action::Override),
+
+				// Pasting is not supported, make it explicit.
+				("Shift+Insert",   Action::Ignore),
+				("Ctrl+Shift+v",   Action::Ignore),
+			].iter().map(|&(name, action)| (Binding::parse(name).unwrap(), action)).collect(),
+
+			passthrough:          Default::default(),
+			passthrough_interval: 150,
+			lock_key:             None,
 		}
 	}
 }
 
 impl Locker {
-	pub fn load(&self, table: &toml::Table) {
+	pub fn load(&self, table: &toml::Table) -> error::Result<()> {
 		if let Some(table) = table.get("locker").and_then(|v| v.as_table()) {
 			if let Some(value) = table.get("display").and_then(|v| v.as_str()) {
 				self.0.write().unwrap().display = Some(value.into());
@@ -69,7 +118,60 @@ impl Locker {
 						Default::default()
 				};
 			}
+
+			if let Some(value) = table.get("lock-key").and_then(|v| v.as_str()) {
+				self.0.write().unwrap().lock_key = Some(Binding::parse(value)
+					.ok_or_else(|| error::Error::Config(format!("invalid lock-key: {}", value)))?);
+			}
+
+			if let Some(table) = table.get("keys").and_then(|v| v.as_table()) {
+				for (name, value) in table {
+					let binding = Binding::parse(name)
+						.ok_or_else(|| error::Error::Config(format!("invalid key: {}", name)))?;
+
+					let action = match value.as_str() {
+						Some("delete")      => Action::Delete,
+						Some("delete-word") => Action::DeleteWord,
+						Some("clear")       => Action::Clear,
+						Some("check")       => Action::Check,
+						Some("override")    => Action::Override,
+						Some("ignore")      => Action::Ignore,
+						_                   =>
+							return Err(error::Error::Config(format!("invalid action for {}: {}", name, value))),
+					};
+
+					let mut data = self.0.write().unwrap();
+					data.keys.retain(|&(b, _)| b != binding);
+					data.keys.push((binding, action));
+				}
+			}
+
+			if let Some(table) = table.get("passthrough").and_then(|v| v.as_table()) {
+				let mut passthrough = Vec::new();
+
+				for (name, value) in table {
+					let binding = Binding::parse(name)
+						.ok_or_else(|| error::Error::Config(format!("invalid passthrough key: {}", name)))?;
+
+					let command = value.as_str()
+						.ok_or_else(|| error::Error::Config(format!("invalid passthrough command for {}", name)))?;
+
+					passthrough.push((binding, command.into()));
+				}
+
+				self.0.write().unwrap().passthrough = passthrough;
+			}
+
+			if let Some(value) = table.get("passthrough-interval").and_then(|v| v.as_integer()) {
+				if value < 0 {
+					return Err(error::Error::Config("passthrough-interval must not be negative".into()));
+				}
+
+				self.0.write().unwrap().passthrough_interval = value as u64;
+			}
 		}
+
+		Ok(())
 	}
 
 	pub fn display(&self) -> Option<String> {
@@ -83,4 +185,70 @@ impl Locker {
 	pub fn on_suspend(&self) -> OnSuspend {
 		self.0.read().unwrap().on_suspend
 	}
+
+	/// The key combination locking the screen.
+	pub fn lock_key(&self) -> Option<Binding> {
+		self.0.read().unwrap().lock_key
+	}
+
+	/// Find the action bound to the pressed keys.
+	pub fn key(&self, pressed: &Binding) -> Option<Action> {
+		self.0.read().unwrap().keys.iter()
+			.find(|&&(ref binding, _)| binding.matches(pressed))
+			.map(|&(_, action)| action)
+	}
+
+	/// Find the command for the pressed passthrough keys.
+	pub fn passthrough(&self, pressed: &Binding) -> Option<String> {
+		self.0.read().unwrap().passthrough.iter()
+			.find(|&&(ref binding, _)| binding.matches(pressed))
+			.map(|&(_, ref command)| command.clone())
+	}
+
+	/// The minimum time in milliseconds between two runs of the same
+	/// passthrough command.
+	pub fn passthrough_interval(&self) -> u64 {
+		self.0.read().unwrap().passthrough_interval
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use toml;
+
+	use super::{Locker, Action};
+	use super::super::Binding;
+
+	fn load(source: &str) -> bool {
+		Locker::default().load(&toml::Parser::new(source).parse().unwrap()).is_ok()
+	}
+
+	#[test]
+	fn invalid() {
+		assert!(load("[locker.keys]\n\"Ctrl+w\" = \"delete\""));
+		assert!(!load("[locker.keys]\n\"Ctrl+NotAKey\" = \"delete\""));
+		assert!(!load("[locker.keys]\n\"Ctrl+w\" = \"explode\""));
+
+		assert!(load("[locker.passthrough]\nXF86AudioMute = \"true\""));
+		assert!(!load("[locker.passthrough]\nNotAKey = \"true\""));
+		assert!(!load("[locker.passthrough]\nXF86AudioMute = 1"));
+
+		assert!(load("[locker]\nlock-key = \"Super+l\""));
+		assert!(!load("[locker]\nlock-key = \"Supr+l\""));
+	}
+
+	#[test]
+	fn keys() {
+		let config = Locker::default();
+		let key    = |name: &str| config.key(&Binding::parse(name).unwrap());
+
+		assert_eq!(Some(Action::Check), key("Return"));
+		assert_eq!(Some(Action::Override), key("Ctrl+Return"));
+		assert_eq!(Some(Action::DeleteWord), key("Ctrl+BackSpace"));
+		assert_eq!(None, key("Shift+Return"));
+
+		// Pasting never inserts anything.
+		assert_eq!(Some(Action::Ignore), key("Shift+Insert"));
+		assert_eq!(Some(Action::Ignore), key("Ctrl+Shift+v"));
+	}
 }
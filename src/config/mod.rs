@@ -17,8 +17,11 @@
 
 use toml;
 
+mod binding;
+pub use self::binding::Binding;
+
 mod locker;
-pub use self::locker::Locker;
+pub use self::locker::{Locker, Action};
 
 mod interface;
 pub use self::interface::Interface;
@@ -27,11 +30,14 @@ mod timer;
 pub use self::timer::Timer;
 
 mod auth;
-pub use self::auth::Auth;
+pub use self::auth::{Auth, Policy, Control};
 
 mod saver;
 pub use self::saver::Saver;
 
+mod audit;
+pub use self::audit::{Audit, Output};
+
 mod config;
 pub use self::config::Config;
 
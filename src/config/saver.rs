@@ -19,6 +19,8 @@ use std::sync::{Arc, RwLock};
 
 use toml;
 
+use error;
+
 #[derive(Clone, Default, Debug)]
 pub struct Saver(pub(super) Arc<RwLock<Data>>);
 
@@ -26,9 +28,13 @@ pub struct Saver(pub(super) Arc<RwLock<Data>>);
 pub(super) struct Data {
 	pub timeout:  u32,
 	pub throttle: bool,
+	pub cycle:    Option<u32>,
+
+	pub crashes: u32,
 
-	pub using: Vec<String>,
-	pub table: toml::Table,
+	pub using:    Vec<String>,
+	pub fallback: Vec<String>,
+	pub table:    toml::Table,
 }
 
 impl Default for Data {
@@ -36,15 +42,19 @@ impl Default for Data {
 		Data {
 			timeout:  5,
 			throttle: false,
+			cycle:    None,
+
+			crashes: 3,
 
-			using: Default::default(),
-			table: Default::default(),
+			using:    Default::default(),
+			fallback: Default::default(),
+			table:    Default::default(),
 		}
 	}
 }
 
 impl Saver {
-	pub fn load(&self, table: &toml::Table) {
+	pub fn load(&self, table: &toml::Table) -> error::Result<()> {
 		if let Some(table) = table.get("saver").and_then(|v| v.as_table()) {
 			if let Some(value) = super::seconds(table.get("timeout")) {
 				self.0.write().unwrap().timeout = value;
@@ -54,6 +64,10 @@ impl Saver {
 				self.0.write().unwrap().throttle = value;
 			}
 
+			if let Some(value) = super::seconds(table.get("cycle")) {
+				self.0.write().unwrap().cycle = Some(value);
+			}
+
 			if let Some(value) = table.get("use").and_then(|v| v.as_slice()) {
 				self.0.write().unwrap().using = value.iter()
 					.filter(|v| v.as_str().is_some())
@@ -61,8 +75,21 @@ impl Saver {
 					.collect();
 			}
 
+			if let Some(value) = table.get("fallback").and_then(|v| v.as_slice()) {
+				self.0.write().unwrap().fallback = value.iter()
+					.filter(|v| v.as_str().is_some())
+					.map(|v| v.as_str().unwrap().into())
+					.collect();
+			}
+
+			if let Some(value) = table.get("crashes").and_then(|v| v.as_integer()) {
+				self.0.write().unwrap().crashes = value as u32;
+			}
+
 			self.0.write().unwrap().table = table.clone();
 		}
+
+		Ok(())
 	}
 
 	/// The timeout for saver requests.
@@ -75,11 +102,26 @@ impl Saver {
 		self.0.read().unwrap().throttle
 	}
 
+	/// How many seconds before savers are replaced with other ones.
+	pub fn cycle(&self) -> Option<u32> {
+		self.0.read().unwrap().cycle
+	}
+
 	/// List of savers being used.
 	pub fn using(&self) -> Vec<String> {
 		self.0.read().unwrap().using.clone()
 	}
 
+	/// List of savers to try when the ones being used fail.
+	pub fn fallback(&self) -> Vec<String> {
+		self.0.read().unwrap().fallback.clone()
+	}
+
+	/// How many times a saver can fail before it's not used anymore.
+	pub fn crashes(&self) -> u32 {
+		self.0.read().unwrap().crashes
+	}
+
 	/// Get the configuration for a specific saver.
 	pub fn get<S: AsRef<str>>(&self, name: S) -> toml::Table {
 		self.0.read().unwrap().table.get(name.as_ref())
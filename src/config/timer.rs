@@ -19,6 +19,8 @@ use std::sync::{Arc, RwLock};
 
 use toml;
 
+use error;
+
 #[derive(Clone, Default, Debug)]
 pub struct Timer(pub(super) Arc<RwLock<Data>>);
 
@@ -42,7 +44,7 @@ impl Default for Data {
 }
 
 impl Timer {
-	pub fn load(&self, table: &toml::Table) {
+	pub fn load(&self, table: &toml::Table) -> error::Result<()> {
 		if let Some(table) = table.get("timer").and_then(|v| v.as_table()) {
 			if let Some(value) = super::seconds(table.get("beat")) {
 				self.0.write().unwrap().beat = value;
@@ -60,6 +62,8 @@ impl Timer {
 				self.0.write().unwrap().blank = Some(value);
 			}
 		}
+
+		Ok(())
 	}
 
 	pub fn beat(&self) -> u32 {
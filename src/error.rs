@@ -33,6 +33,7 @@ pub enum Error {
 	Nul(ffi::NulError),
 	Unknown,
 	Parse,
+	Config(String),
 
 	X(X),
 	DBus(DBus),
@@ -64,12 +65,27 @@ pub enum Grab {
 #[derive(Clone, Debug)]
 pub enum Auth {
 	UnknownUser,
+	UnknownMethod(String),
+	AccountExpired,
+	PasswordExpired,
 
 	#[cfg(feature = "auth-internal")]
 	Internal(auth::Internal),
 
 	#[cfg(feature = "auth-pam")]
 	Pam(auth::Pam),
+
+	#[cfg(feature = "auth-totp")]
+	Totp(auth::Totp),
+
+	#[cfg(feature = "auth-exec")]
+	Exec(auth::Exec),
+
+	#[cfg(any(test, feature = "auth-mock"))]
+	Mock(auth::Mock),
+
+	#[cfg(feature = "auth-fprint")]
+	Fprint(auth::Fprint),
 }
 
 pub mod auth {
@@ -80,11 +96,40 @@ pub mod auth {
 	#[cfg(feature = "auth-internal")]
 	pub enum Internal {
 		MissingPassword,
+		InvalidHash,
 	}
 
 	#[derive(Clone, Debug)]
 	#[cfg(feature = "auth-pam")]
 	pub struct Pam(pub pam::PamReturnCode);
+
+	#[derive(Clone, Debug)]
+	#[cfg(feature = "auth-totp")]
+	pub enum Totp {
+		MissingSecret,
+		InvalidSecret,
+		InvalidSetting(String),
+		Permissions,
+	}
+
+	#[derive(Clone, Debug)]
+	#[cfg(feature = "auth-exec")]
+	pub enum Exec {
+		MissingCommand,
+	}
+
+	#[derive(Clone, Debug)]
+	#[cfg(any(test, feature = "auth-mock"))]
+	pub enum Mock {
+		InvalidStep(String),
+		Scripted,
+	}
+
+	#[derive(Clone, Debug)]
+	#[cfg(feature = "auth-fprint")]
+	pub enum Fprint {
+		NoDevice,
+	}
 }
 
 impl From<io::Error> for Error {
@@ -173,6 +218,34 @@ impl From<auth::Pam> for Error {
 	}
 }
 
+#[cfg(feature = "auth-totp")]
+impl From<auth::Totp> for Error {
+	fn from(value: auth::Totp) -> Self {
+		Error::Auth(Auth::Totp(value))
+	}
+}
+
+#[cfg(feature = "auth-exec")]
+impl From<auth::Exec> for Error {
+	fn from(value: auth::Exec) -> Self {
+		Error::Auth(Auth::Exec(value))
+	}
+}
+
+#[cfg(any(test, feature = "auth-mock"))]
+impl From<auth::Mock> for Error {
+	fn from(value: auth::Mock) -> Self {
+		Error::Auth(Auth::Mock(value))
+	}
+}
+
+#[cfg(feature = "auth-fprint")]
+impl From<auth::Fprint> for Error {
+	fn from(value: auth::Fprint) -> Self {
+		Error::Auth(Auth::Fprint(value))
+	}
+}
+
 impl fmt::Display for Error {
 	fn fmt(&self, f: &mut fmt::Formatter) -> ::std::result::Result<(), fmt::Error> {
 		f.write_str(error::Error::description(self))
@@ -197,6 +270,9 @@ impl error::Error for Error {
 			Error::Parse =>
 				"Parse error.",
 
+			Error::Config(ref msg) =>
+				msg.as_ref(),
+
 			Error::X(ref err) => match *err {
 				X::Request(..) =>
 					"An X request failed.",
@@ -234,15 +310,63 @@ impl error::Error for Error {
 				Auth::UnknownUser =>
 					"Unknown user.",
 
+				Auth::UnknownMethod(..) =>
+					"Unknown or unavailable authorization method.",
+
+				Auth::AccountExpired =>
+					"The account has expired.",
+
+				Auth::PasswordExpired =>
+					"The password has expired.",
+
 				#[cfg(feature = "auth-internal")]
 				Auth::Internal(ref err) => match *err {
 					auth::Internal::MissingPassword =>
 						"Missing internal password.",
+
+					auth::Internal::InvalidHash =>
+						"Invalid internal password hash.",
 				},
 
 				#[cfg(feature = "auth-pam")]
 				Auth::Pam(auth::Pam(_code)) =>
 					"PAM error.",
+
+				#[cfg(feature = "auth-totp")]
+				Auth::Totp(ref err) => match *err {
+					auth::Totp::MissingSecret =>
+						"Missing TOTP secret.",
+
+					auth::Totp::InvalidSecret =>
+						"Invalid TOTP secret.",
+
+					auth::Totp::InvalidSetting(..) =>
+						"Invalid TOTP setting.",
+
+					auth::Totp::Permissions =>
+						"The TOTP secret is accessible by other users.",
+				},
+
+				#[cfg(feature = "auth-exec")]
+				Auth::Exec(ref err) => match *err {
+					auth::Exec::MissingCommand =>
+						"Missing command to execute.",
+				},
+
+				#[cfg(any(test, feature = "auth-mock"))]
+				Auth::Mock(ref err) => match *err {
+					auth::Mock::InvalidStep(..) =>
+						"Invalid mock script step.",
+
+					auth::Mock::Scripted =>
+						"Scripted mock error.",
+				},
+
+				#[cfg(feature = "auth-fprint")]
+				Auth::Fprint(ref err) => match *err {
+					auth::Fprint::NoDevice =>
+						"No fingerprint reader available.",
+				},
 			},
 		}
 	}
@@ -139,6 +139,19 @@ pub enum Signal {
 
 	/// An authentication request was initiated or completed.
 	AuthenticationRequest(bool),
+
+	/// The session was unlocked by another user.
+	Override(String),
+
+	/// The session was unlocked after the given failed attempts.
+	Unlocked(u32),
+
+	/// A saver failed to start or crashed.
+	SaverFailed {
+		name:   String,
+		reason: String,
+		stderr: String,
+	},
 }
 
 impl Interface {
@@ -418,6 +431,12 @@ impl Interface {
 				let begin  = Arc::new(f.signal("AuthenticationRequestBegin"));
 				let end    = Arc::new(f.signal("AuthenticationRequestEnd"));
 
+				// ScreenRuster signals.
+				let over     = Arc::new(f.signal("Override").sarg::<String, _>("user"));
+				let unlocked = Arc::new(f.signal("Unlocked").sarg::<u32, _>("failures"));
+				let failed   = Arc::new(f.signal("SaverFailed")
+					.sarg::<String, _>("name").sarg::<String, _>("reason").sarg::<String, _>("stderr"));
+
 				let tree = f.tree()
 					// ScreenRuster interface.
 					.add(f.object_path("/meh/rust/ScreenSaver").introspectable().add(f.interface("meh.rust.ScreenSaver")
@@ -472,7 +491,11 @@ impl Interface {
 							else {
 								Err(dbus::tree::MethodErr::no_arg())
 							}
-						}).inarg::<u32, _>("cookie"))))
+						}).inarg::<u32, _>("cookie"))
+
+						.add_s_arc(over.clone())
+						.add_s_arc(unlocked.clone())
+						.add_s_arc(failed.clone())))
 
 					// GNOME screensaver interface.
 					.add(f.object_path("/org/gnome/ScreenSaver").introspectable().add(f.interface("org.gnome.ScreenSaver")
@@ -651,6 +674,18 @@ impl Interface {
 								Signal::AuthenticationRequest(false) => {
 									end.msg()
 								}
+
+								Signal::Override(user) => {
+									over.msg().append1(user)
+								}
+
+								Signal::Unlocked(failures) => {
+									unlocked.msg().append1(failures)
+								}
+
+								Signal::SaverFailed { name, reason, stderr } => {
+									failed.msg().append2(name, reason).append1(stderr)
+								}
 							}).unwrap();
 						}
 					}
@@ -0,0 +1,246 @@
+// Copyleft (ↄ) meh. <meh@schizofreni.co> | http://meh.schizofreni.co
+//
+// This file is part of screenruster.
+//
+// screenruster is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// screenruster is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+use std::iter;
+use std::cmp;
+
+use xcb;
+
+use error;
+use saver::Password;
+use platform::keyboard;
+use super::{Display, Window};
+
+/// The most characters shown for the masked password.
+const MASK: usize = 32;
+
+/// The most characters in a core text request.
+const LINE: usize = 255;
+
+/// The Unicode version of `fixed`, so messages and layout names aren't limited
+/// to Latin-1.
+const FONT: &'static str = "-misc-fixed-medium-r-semicondensed--13-*-*-*-*-*-iso10646-1";
+
+/// A minimal unlock dialog drawn on windows without a saver, so there's
+/// always some feedback while typing.
+pub struct Dialog {
+	display: Arc<Display>,
+	window:  u32,
+	gc:      u32,
+	font:    u32,
+	black:   u32,
+	white:   u32,
+
+	width:  i16,
+	ascent: i16,
+	height: i16,
+
+	state:    State,
+	length:   usize,
+	message:  Option<String>,
+	keyboard: Option<keyboard::Status>,
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+enum State {
+	Prompt,
+	Checking,
+	Success,
+	Failure,
+	Timeout,
+}
+
+impl Dialog {
+	/// Create a dialog drawing on the given window.
+	pub fn create(display: Arc<Display>, window: &Window) -> error::Result<Dialog> {
+		let screen = display.get_setup().roots().nth(window.screen() as usize).unwrap();
+		let font   = display.generate_id();
+
+		if xcb::open_font_checked(&display, font, FONT).request_check().is_err() {
+			xcb::open_font_checked(&display, font, "fixed").request_check()?;
+		}
+
+		let (width, ascent, height) = {
+			let reply = xcb::query_font(&display, font).get_reply()?;
+
+			(reply.max_bounds().character_width(),
+			 reply.font_ascent(),
+			 reply.font_ascent() + reply.font_descent())
+		};
+
+		let gc = display.generate_id();
+		xcb::create_gc(&display, gc, window.id(), &[
+			(xcb::GC_FOREGROUND, screen.white_pixel()),
+			(xcb::GC_BACKGROUND, screen.black_pixel()),
+			(xcb::GC_FONT, font)]);
+
+		Ok(Dialog {
+			display: display.clone(),
+			window:  window.id(),
+			gc:      gc,
+			font:    font,
+			black:   screen.black_pixel(),
+			white:   screen.white_pixel(),
+
+			width:  width,
+			ascent: ascent,
+			height: height,
+
+			state:    State::Prompt,
+			length:   0,
+			message:  None,
+			keyboard: None,
+		})
+	}
+
+	/// Update the dialog for the given password event.
+	pub fn password(&mut self, password: Password) {
+		match password {
+			Password::Insert => {
+				self.state   = State::Prompt;
+				self.length += 1;
+			}
+
+			Password::Delete => {
+				self.state  = State::Prompt;
+				self.length = self.length.saturating_sub(1);
+			}
+
+			Password::Reset => {
+				self.state  = State::Prompt;
+				self.length = 0;
+			}
+
+			Password::Check => {
+				self.state   = State::Checking;
+				self.message = None;
+			}
+
+			Password::Success => {
+				self.state  = State::Success;
+				self.length = 0;
+			}
+
+			Password::Failure => {
+				self.state  = State::Failure;
+				self.length = 0;
+			}
+		}
+
+		self.draw();
+	}
+
+	/// The authentication timed out.
+	pub fn timeout(&mut self) {
+		self.state  = State::Timeout;
+		self.length = 0;
+
+		self.draw();
+	}
+
+	/// Show a message from the authentication.
+	pub fn message<S: Into<String>>(&mut self, message: S) {
+		self.message = Some(message.into());
+		self.draw();
+	}
+
+	/// Show the keyboard layout and whether Caps Lock is on.
+	pub fn keyboard(&mut self, status: keyboard::Status) {
+		self.keyboard = Some(status);
+		self.draw();
+	}
+
+	/// Draw the dialog centered in the window.
+	pub fn draw(&self) {
+		let (width, height) = if let Ok(reply) = xcb::get_geometry(&self.display, self.window).get_reply() {
+			(reply.width() as i16, reply.height() as i16)
+		}
+		else {
+			return;
+		};
+
+		let title = match self.state {
+			State::Prompt   => "Enter password to unlock",
+			State::Checking => "Checking...",
+			State::Success  => "Unlocked",
+			State::Failure  => "Authentication failed",
+			State::Timeout  => "Authentication timed out",
+		};
+
+		let mut mask = iter::repeat('*').take(cmp::min(self.length, MASK)).collect::<String>();
+
+		if self.length > MASK {
+			mask.push('>');
+		}
+
+		let keyboard = match self.keyboard {
+			Some(ref status) if status.caps =>
+				format!("{} (Caps Lock is on)", status.layout),
+
+			Some(ref status) =>
+				status.layout.clone(),
+
+			None =>
+				String::new(),
+		};
+
+		let lines = [title, mask.as_str(), self.message.as_ref().map(|m| m.as_str()).unwrap_or(""), keyboard.as_str()]
+			.iter().map(|line| line.chars().take(LINE).collect::<String>())
+			.collect::<Vec<String>>();
+
+		let columns = cmp::max(40, lines.iter().map(|l| l.chars().count()).max().unwrap_or(0)) as i16;
+
+		let box_width  = (columns + 4) * self.width;
+		let box_height = (lines.len() as i16 * 2 + 1) * self.height;
+		let x          = (width - box_width) / 2;
+		let y          = (height - box_height) / 2;
+
+		// Clear the whole window, savers may have left things behind.
+		xcb::change_gc(&self.display, self.gc, &[(xcb::GC_FOREGROUND, self.black)]);
+		xcb::poly_fill_rectangle(&self.display, self.window, self.gc, &[
+			xcb::Rectangle::new(0, 0, width as u16, height as u16)]);
+
+		xcb::change_gc(&self.display, self.gc, &[(xcb::GC_FOREGROUND, self.white)]);
+		xcb::poly_rectangle(&self.display, self.window, self.gc, &[
+			xcb::Rectangle::new(x, y, box_width as u16, box_height as u16)]);
+
+		for (i, line) in lines.iter().enumerate() {
+			let left = x + (box_width - line.chars().count() as i16 * self.width) / 2;
+			let top  = y + (i as i16 * 2 + 1) * self.height + self.ascent;
+
+			xcb::image_text_16(&self.display, self.window, self.gc, left, top, &text(line));
+		}
+
+		self.display.flush();
+	}
+}
+
+/// Encode the line as the 16 bit characters of a core text request, anything
+/// outside of the basic plane is shown as a question mark.
+fn text(line: &str) -> Vec<xcb::Char2b> {
+	line.chars().map(|ch| if (ch as u32) > 0xffff { '?' as u32 } else { ch as u32 })
+		.map(|ch| xcb::Char2b::new((ch >> 8) as u8, ch as u8))
+		.collect()
+}
+
+impl Drop for Dialog {
+	fn drop(&mut self) {
+		xcb::free_gc(&self.display, self.gc);
+		xcb::close_font(&self.display, self.font);
+	}
+}
@@ -24,11 +24,35 @@ use error;
 use config;
 use platform;
 
+/// The modifiers of Caps Lock and Num Lock.
+const LOCKS: [u32; 4] = [0, xcb::MOD_MASK_LOCK, xcb::MOD_MASK_2, xcb::MOD_MASK_LOCK | xcb::MOD_MASK_2];
+
 pub struct Display {
 	display: Arc<platform::Display>,
 
-	randr: bool,
-	dpms:  bool,
+	randr:   bool,
+	version: (u32, u32),
+	dpms:    bool,
+}
+
+/// The geometry of an active RandR CRTC.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct Monitor {
+	pub crtc:   u32,
+	pub x:      i16,
+	pub y:      i16,
+	pub width:  u16,
+	pub height: u16,
+}
+
+impl Monitor {
+	/// Check if the given point is on the monitor.
+	pub fn contains(&self, x: i16, y: i16) -> bool {
+		let (x, y) = (x as i32, y as i32);
+
+		x >= self.x as i32 && x < self.x as i32 + self.width as i32 &&
+		y >= self.y as i32 && y < self.y as i32 + self.height as i32
+	}
 }
 
 unsafe impl Send for Display { }
@@ -39,14 +63,17 @@ impl Display {
 	pub fn open(config: config::Locker) -> error::Result<Arc<Display>> {
 		let     display = platform::Display::open(config.display())?;
 		let     randr   = display.get_extension_data(xcb::randr::id());
+		let mut version = (0, 0);
 		let mut dpms    = display.get_extension_data(xcb::dpms::id());
 
 		if randr.is_some() {
-			let version = xcb::randr::query_version(&display, 1, 1).get_reply()?;
+			let reply = xcb::randr::query_version(&display, 1, 3).get_reply()?;
 
-			if version.major_version() < 1 || (version.major_version() >= 1 && version.minor_version() < 1) {
+			if reply.major_version() < 1 || (reply.major_version() >= 1 && reply.minor_version() < 1) {
 				return Err(error::X::MissingExtension.into());
 			}
+
+			version = (reply.major_version(), reply.minor_version());
 		}
 
 		if let Some(ext) = dpms.take() {
@@ -58,8 +85,9 @@ impl Display {
 		let display = Arc::new(Display {
 			display: display,
 
-			randr: randr.is_some(),
-			dpms:  dpms.is_some(),
+			randr:   randr.is_some(),
+			version: version,
+			dpms:    dpms.is_some(),
 		});
 
 		display.sanitize();
@@ -77,6 +105,148 @@ impl Display {
 		}
 	}
 
+	/// Get the active CRTCs of the given screen, empty if RandR 1.2 is missing.
+	///
+	/// CRTCs showing the same area, like cloned outputs, are only returned once.
+	pub fn monitors(&self, screen: i32) -> Vec<Monitor> {
+		if !self.randr || self.version < (1, 2) {
+			return Vec::new();
+		}
+
+		let root = if let Some(screen) = self.get_setup().roots().nth(screen as usize) {
+			screen.root()
+		}
+		else {
+			return Vec::new();
+		};
+
+		// Querying the current resources doesn't poll the outputs, but it's only
+		// available since 1.3.
+		let resources = if self.version >= (1, 3) {
+			xcb::randr::get_screen_resources_current(self, root).get_reply()
+				.map(|r| (r.crtcs().to_vec(), r.config_timestamp()))
+		}
+		else {
+			xcb::randr::get_screen_resources(self, root).get_reply()
+				.map(|r| (r.crtcs().to_vec(), r.config_timestamp()))
+		};
+
+		let (crtcs, timestamp) = if let Ok(value) = resources {
+			value
+		}
+		else {
+			return Vec::new();
+		};
+
+		let mut monitors = Vec::new(): Vec<Monitor>;
+
+		for crtc in crtcs {
+			if let Ok(info) = xcb::randr::get_crtc_info(self, crtc, timestamp).get_reply() {
+				// Disabled CRTCs have no mode or outputs.
+				if info.mode() == 0 || info.num_outputs() == 0 || info.width() == 0 || info.height() == 0 {
+					continue;
+				}
+
+				let monitor = Monitor {
+					crtc:   crtc,
+					x:      info.x(),
+					y:      info.y(),
+					width:  info.width(),
+					height: info.height(),
+				};
+
+				if !monitors.iter().any(|m| (m.x, m.y, m.width, m.height) == (monitor.x, monitor.y, monitor.width, monitor.height)) {
+					monitors.push(monitor);
+				}
+			}
+		}
+
+		monitors
+	}
+
+	/// Get the CRTC showing the primary output of the given screen, if any.
+	pub fn primary(&self, screen: i32) -> Option<u32> {
+		if !self.randr || self.version < (1, 3) {
+			return None;
+		}
+
+		let root = if let Some(screen) = self.get_setup().roots().nth(screen as usize) {
+			screen.root()
+		}
+		else {
+			return None;
+		};
+
+		let output = match xcb::randr::get_output_primary(self, root).get_reply() {
+			Ok(ref reply) if reply.output() != 0 =>
+				reply.output(),
+
+			_ =>
+				return None
+		};
+
+		match xcb::randr::get_output_info(self, output, xcb::CURRENT_TIME).get_reply() {
+			Ok(ref reply) if reply.crtc() != 0 =>
+				Some(reply.crtc()),
+
+			_ =>
+				None
+		}
+	}
+
+	/// Get the root window the pointer is on and its position.
+	pub fn pointer(&self) -> Option<(u32, i16, i16)> {
+		let root = if let Some(screen) = self.get_setup().roots().nth(self.screen() as usize) {
+			screen.root()
+		}
+		else {
+			return None;
+		};
+
+		xcb::query_pointer(self, root).get_reply().ok()
+			.map(|reply| (reply.root(), reply.root_x(), reply.root_y()))
+	}
+
+	/// Listen for monitors being added, removed or changed on the given root
+	/// window.
+	pub fn watch(&self, root: u32) {
+		if self.randr && self.version >= (1, 2) {
+			xcb::randr::select_input(self, root,
+				(xcb::randr::NOTIFY_MASK_CRTC_CHANGE | xcb::randr::NOTIFY_MASK_OUTPUT_CHANGE) as u16);
+		}
+	}
+
+	/// Grab the given key on the root window, whatever the state of Caps Lock
+	/// and Num Lock, returns `false` if another client already grabbed it.
+	pub fn grab_key(&self, root: u32, code: u8, modifiers: u16) -> bool {
+		let mut grabbed = true;
+
+		for &extra in &LOCKS {
+			let result = xcb::grab_key_checked(self, true, root, modifiers | extra as u16, code,
+				xcb::GRAB_MODE_ASYNC as u8, xcb::GRAB_MODE_ASYNC as u8).request_check();
+
+			match result {
+				Ok(()) =>
+					(),
+
+				Err(ref err) if err.error_code() == xcb::ACCESS =>
+					grabbed = false,
+
+				Err(err) =>
+					warn!("locker: could not grab key {}: {}", code, err.error_code()),
+			}
+		}
+
+		grabbed
+	}
+
+	/// Release the grab of the given key on the root window.
+	pub fn ungrab_key(&self, root: u32, code: u8, modifiers: u16) {
+		for &extra in &LOCKS {
+			xcb::ungrab_key(self, code, root, modifiers | extra as u16);
+		}
+	}
+
 	/// Get the DPMS extension data.
 	pub fn dpms(&self) -> Option<xcb::QueryExtensionData> {
 		if self.dpms {
@@ -15,9 +15,11 @@
 // You should have received a copy of the GNU General Public License
 // along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::thread;
+use std::process::{Command, Stdio};
 use std::ops::Deref;
+use std::time::{Instant, Duration};
 use std::sync::mpsc::{Receiver, Sender, SendError, channel};
 
 use rand::{self, Rng};
@@ -26,16 +28,22 @@ use xkbcommon::xkb;
 use xkbcommon::xkb::keysyms as key;
 
 use error;
-use config::Config;
+use config::{Config, Binding, Action};
 use api;
+use auth::Reason;
 use timer;
 use saver::{self, Saver, Safety, Password, Pointer};
-use super::{Display, Window};
+use secret::{self, Secret};
+use super::{Display, Monitor, Window, Dialog};
 use platform::{self, Keyboard};
 
+/// Timeout ID used to cycle the savers, out of the range of window IDs.
+const CYCLE: u64 = ::std::u64::MAX;
+
 pub struct Locker {
 	receiver: Receiver<Response>,
 	sender:   Sender<Request>,
+	display:  String,
 }
 
 #[derive(Clone)]
@@ -48,34 +56,92 @@ pub enum Request {
 
 	Start,
 	Lock,
+	Cycle,
 	Auth(bool),
+	AuthTimeout,
+	Delay(u64),
+	Prompt(String, bool),
+	Info(String),
+	Error(String),
+	Refused(Reason),
 	Stop,
+
+	Reload,
 }
 
 #[derive(Clone)]
 pub enum Response {
 	Timeout(timer::Timeout),
 	Activity,
-	Password(String),
+	Password(Secret),
+	Override(Secret),
+	Answer(Secret),
+	Lock,
 	Stopped,
+
+	Failed {
+		name:   String,
+		reason: String,
+		stderr: Vec<String>,
+	},
 }
 
 impl Locker {
 	pub fn spawn(config: Config) -> error::Result<Locker> {
 		let     display  = Display::open(config.locker())?;
+		let     name     = display.name().to_owned();
 		let mut keyboard = Keyboard::new((*display).clone())?;
 		let mut windows  = HashMap::new(): HashMap<u32, Window>;
 		let mut savers   = HashMap::new(): HashMap<u32, Saver>;
+		let mut dialogs  = HashMap::new(): HashMap<u32, Dialog>;
 		let mut checking = false;
-		let mut password = String::new();
-
+		let mut prompt   = false;
+		let mut delay    = None: Option<Instant>;
+		let mut password = Secret::new();
+		let mut active   = false;
+		let mut locked   = false;
+		let mut index    = 0;
+
+		// Savers that failed, and the ones tried on each window since starting.
+		let mut crashes   = HashMap::new(): HashMap<String, u32>;
+		let mut blacklist = HashSet::new(): HashSet<String>;
+		let mut tried     = HashMap::new(): HashMap<u32, Vec<String>>;
+
+		// Windows whose saver is being replaced.
+		let mut cycling = HashSet::new(): HashSet<u32>;
+
+		// When the passthrough keys were last used.
+		let mut passed = HashMap::new(): HashMap<Binding, Instant>;
+
+		// Create a window for every monitor, or for every screen if RandR is not
+		// available.
 		for screen in 0 .. display.screens() {
-			let window = Window::create(display.clone(), screen as i32)?;
+			let monitors = display.monitors(screen as i32);
+
+			if monitors.is_empty() {
+				let window = Window::create(display.clone(), screen as i32, index, None)?;
+				windows.insert(window.id(), window);
+				index += 1;
+			}
+			else {
+				for monitor in monitors {
+					let window = Window::create(display.clone(), screen as i32, index, Some(monitor))?;
+					windows.insert(window.id(), window);
+					index += 1;
+				}
+			}
 
-			display.observe(window.root());
-			windows.insert(window.id(), window);
+			let root = display.get_setup().roots().nth(screen as usize).unwrap().root();
+
+			display.observe(root);
+			display.watch(root);
 		}
 
+		// Grab the lock keys on every root window.
+		let mut locks = lock_keys(&config);
+		let mut grabs = grab_locks(&display, &keyboard, &locks);
+		display.flush();
+
 		let (sender,   i_receiver)   = channel();
 		let (i_sender, receiver)   = channel();
 		let (s_sender, s_receiver) = channel();
@@ -95,6 +161,69 @@ impl Locker {
 				);
 			}
 
+			macro_rules! dialog {
+				(list) => (
+					dialogs.values_mut()
+				);
+
+				// Show the built-in dialog on locked windows without a saver, and hide
+				// it from the others.
+				(update) => ({
+					for (&id, window) in &windows {
+						if !locked || !window.is_locked() || savers.contains_key(&id) {
+							dialogs.remove(&id);
+							continue;
+						}
+
+						if dialogs.contains_key(&id) {
+							continue;
+						}
+
+						match Dialog::create(display.clone(), window) {
+							Ok(mut dialog) => {
+								dialog.keyboard(keyboard.status());
+								dialogs.insert(id, dialog);
+							}
+
+							Err(err) => {
+								warn!("could not create dialog: {:?}", err);
+							}
+						}
+					}
+
+					let stale = dialogs.keys().filter(|id| !windows.contains_key(id)).cloned().collect::<Vec<u32>>();
+
+					for id in stale {
+						dialogs.remove(&id);
+					}
+				});
+			}
+
+			// Keep track of a failed saver and blacklist it when it keeps failing.
+			macro_rules! failed {
+				($name:expr, $reason:expr, $stderr:expr) => ({
+					let name   = $name;
+					let reason = $reason;
+					let count  = {
+						let count = crashes.entry(name.clone()).or_insert(0);
+						*count += 1;
+						*count
+					};
+
+					warn!("saver {} failed: {}", name, reason);
+
+					if count >= config.saver().crashes() && blacklist.insert(name.clone()) {
+						warn!("saver {} failed {} times, not using it until reload", name, count);
+					}
+
+					sender.send(Response::Failed {
+						name:   name,
+						reason: reason,
+						stderr: $stderr,
+					}).unwrap();
+				});
+			}
+
 			macro_rules! saver {
 				(list) => (
 					savers.values_mut()
@@ -104,8 +233,81 @@ impl Locker {
 					savers.insert($id, $saver);
 				);
 
+				// Spawn a saver on the window, or blank it if none is available.
+				//
+				// A random saver is picked from the used ones, if it fails the next
+				// ones are tried in order and then the fallback ones.
+				(start $window:expr) => ({
+					let window = $window;
+					let id     = window.id();
+					let mut started = false;
+
+					let mut chain = config.saver().using();
+
+					if !chain.is_empty() {
+						let start = rand::thread_rng().gen_range(0, chain.len());
+						let rest  = chain.split_off(start);
+
+						chain = rest.into_iter().chain(chain.into_iter()).collect();
+					}
+
+					for name in config.saver().fallback() {
+						if !chain.contains(&name) {
+							chain.push(name);
+						}
+					}
+
+					for name in chain {
+						if blacklist.contains(&name) || tried.get(&id).map_or(false, |t| t.contains(&name)) {
+							continue;
+						}
+
+						tried.entry(id).or_insert_with(Vec::new).push(name.clone());
+
+						match Saver::spawn(&name) {
+							Err(err) => {
+								failed!(name, format!("{}", err), Vec::new());
+							}
+
+							Ok(mut saver) => {
+								sender.send(Response::Timeout(timer::Timeout::Set {
+									id:      id as u64,
+									seconds: config.saver().timeout() as u64,
+								})).unwrap();
+
+								let receiver = saver.take().unwrap();
+								let sender   = s_sender.clone();
+
+								thread::spawn(move || {
+									while let Ok(event) = receiver.recv() {
+										sender.send((id, event)).unwrap();
+									}
+								});
+
+								saver.config(config.saver().get(&name)).unwrap();
+								saver.target(display.name(), window.screen(), id as u64).unwrap();
+								saver.keyboard(keyboard.status()).unwrap();
+
+								if config.saver().throttle() {
+									saver.throttle(true).unwrap();
+								}
+
+								saver!(add id => saver);
+								started = true;
+
+								break;
+							}
+						}
+					}
+
+					if !started {
+						window.lock().unwrap();
+						window.blank();
+					}
+				});
+
 				(remove $id:expr) => (
-					savers.remove(&$id);
+					savers.remove(&$id)
 				);
 
 				(safety $id:expr) => (
@@ -135,6 +337,163 @@ impl Locker {
 				);
 			}
 
+			// Reconcile the windows with the current monitors, creating, moving and
+			// destroying windows and savers as needed.
+			macro_rules! monitors {
+				() => ({
+					for screen in 0 .. display.screens() as i32 {
+						let monitors = display.monitors(screen);
+
+						// Windows covering a monitor that went away, or covering the whole
+						// screen when monitors are available again.
+						let stale = windows.values()
+							.filter(|w| w.screen() == screen && match w.monitor() {
+								Some(current) => !monitors.iter().any(|m| m.crtc == current.crtc),
+								None          => !monitors.is_empty(),
+							})
+							.map(|w| w.id())
+							.collect::<Vec<u32>>();
+
+						// Follow monitors that moved or changed resolution.
+						for window in window!(list) {
+							if window.screen() != screen {
+								continue;
+							}
+
+							if let Some(current) = window.monitor() {
+								if let Some(&monitor) = monitors.iter().find(|m| m.crtc == current.crtc) {
+									if monitor != current {
+										window.cover(monitor);
+
+										if let Some(saver) = saver!(? window.id()) {
+											saver.resize(monitor.width as u32, monitor.height as u32).unwrap();
+										}
+									}
+								}
+							}
+						}
+
+						// Cover new monitors, or the whole screen if none is left.
+						let mut added = Vec::new(): Vec<Option<Monitor>>;
+
+						if monitors.is_empty() {
+							if !windows.values().any(|w| w.screen() == screen && w.monitor().is_none()) {
+								added.push(None);
+							}
+						}
+						else {
+							for monitor in &monitors {
+								if !windows.values().any(|w| w.screen() == screen && w.monitor().map_or(false, |m| m.crtc == monitor.crtc)) {
+									added.push(Some(*monitor));
+								}
+							}
+						}
+
+						for monitor in added {
+							let mut window = match Window::create(display.clone(), screen, index, monitor) {
+								Ok(window) =>
+									window,
+
+								Err(err) => {
+									error!("could not create window for {:?}: {:?}", monitor, err);
+									continue;
+								}
+							};
+
+							index += 1;
+
+							// Cover the new monitor right away if the screen is locked.
+							if locked {
+								window.lock().unwrap();
+								window.blank();
+							}
+
+							let id = window.id();
+							windows.insert(id, window);
+
+							if active {
+								saver!(start window!(id));
+
+								if locked {
+									if let Some(saver) = saver!(? id) {
+										saver.lock().unwrap();
+									}
+								}
+							}
+						}
+
+						// Destroy stale windows only after the new ones are up, so nothing
+						// is left uncovered in between.
+						for id in stale {
+							if let Some(mut saver) = savers.remove(&id) {
+								sender.send(Response::Timeout(timer::Timeout::Cancel { id: id as u64 })).unwrap();
+								saver.kill();
+							}
+
+							windows.remove(&id);
+							tried.remove(&id);
+							cycling.remove(&id);
+						}
+					}
+
+					// The grabs are lost if the window holding them was destroyed, so
+					// grab again on one that's left, preferably where the user is.
+					if locked {
+						let mut candidates = windows.values().filter(|w| w.is_locked())
+							.map(|w| (w.id(), w.root(), w.monitor())).collect::<Vec<_>>();
+						candidates.sort_by_key(|&(id, _, _)| id);
+
+						let primary = (0 .. display.screens() as i32)
+							.filter_map(|screen| display.primary(screen)).collect::<Vec<_>>();
+
+						let default = display.get_setup().roots().nth(display.screen() as usize)
+							.map(|screen| screen.root()).unwrap_or(0);
+
+						if let Some(id) = holder(&candidates, display.pointer(), &primary, default) {
+							let window = windows.get_mut(&id).unwrap();
+
+							window.regrab();
+							saver!(safety on window);
+						}
+					}
+
+					dialog!(update);
+					display.flush();
+				});
+			}
+
+			// Stop the running savers so they get replaced with other ones once
+			// they exit, the windows are kept locked in the meantime.
+			macro_rules! cycle {
+				() => ({
+					let available = config.saver().using().iter()
+						.filter(|name| !blacklist.contains(*name)).count();
+
+					for (&id, saver) in &mut savers {
+						if !saver.was_started() || saver.was_stopped() {
+							continue;
+						}
+
+						// Avoid picking the same saver again if there are others.
+						tried.insert(id, if available > 1 {
+							vec![saver.name().to_owned()]
+						}
+						else {
+							Vec::new()
+						});
+
+						cycling.insert(id);
+
+						sender.send(Response::Timeout(timer::Timeout::Set {
+							id:      id as u64,
+							seconds: config.saver().timeout() as u64,
+						})).unwrap();
+
+						saver.stop().unwrap();
+					}
+				});
+			}
+
 			let x = platform::display::sink(&display);
 
 			loop {
@@ -142,6 +501,19 @@ impl Locker {
 					// Handle control events.
 					event = receiver.recv() => {
 						match event.unwrap() {
+							Request::Timeout { id: CYCLE } => {
+								if active {
+									cycle!();
+
+									if let Some(seconds) = config.saver().cycle() {
+										sender.send(Response::Timeout(timer::Timeout::Set {
+											id:      CYCLE,
+											seconds: seconds as u64,
+										})).unwrap();
+									}
+								}
+							}
+
 							Request::Timeout { id } => {
 								if let Some(saver) = saver!(? id as u32) {
 									saver.kill();
@@ -184,64 +556,149 @@ impl Locker {
 									saver.blank(!value).unwrap();
 								}
 
+								if value {
+									for dialog in dialog!(list) {
+										dialog.draw();
+									}
+								}
+
 								display.power(value);
 							}
 
 							Request::Start => {
+								active = true;
+								tried.clear();
+
 								for window in window!(list) {
-									if !config.saver().using().is_empty() {
-										let name = &config.saver().using()[rand::thread_rng().gen_range(0, config.saver().using().len())];
+									saver!(start window);
+								}
 
-										if let Ok(mut saver) = Saver::spawn(&name) {
-											let id = window.id();
+								if let Some(seconds) = config.saver().cycle() {
+									sender.send(Response::Timeout(timer::Timeout::Set {
+										id:      CYCLE,
+										seconds: seconds as u64,
+									})).unwrap();
+								}
+							}
 
-											sender.send(Response::Timeout(timer::Timeout::Set {
-												id:      id as u64,
-												seconds: config.saver().timeout() as u64,
-											})).unwrap();
+							Request::Cycle => {
+								if active {
+									cycle!();
+								}
+							}
 
-											let receiver = saver.take().unwrap();
-											let sender   = s_sender.clone();
+							Request::Lock => {
+								locked = true;
+								prompt = false;
 
-											thread::spawn(move || {
-												while let Ok(event) = receiver.recv() {
-													sender.send((id, event)).unwrap();
-												}
-											});
+								for saver in saver!(list) {
+									saver.lock().unwrap();
+								}
+
+								dialog!(update);
+							}
 
-											saver.config(config.saver().get(&name)).unwrap();
-											saver.target(display.name(), window.screen(), id as u64).unwrap();
+							// Ignore input until the delay expires, it comes after the
+							// failure or alone if the attempt was refused.
+							Request::Delay(seconds) => {
+								delay    = Some(Instant::now() + Duration::from_secs(seconds));
+								checking = true;
+								prompt   = false;
 
-											if config.saver().throttle() {
-												saver.throttle(true).unwrap();
-											}
+								for saver in saver!(list) {
+									saver.delay(seconds).unwrap();
+								}
 
-											saver!(add id => saver);
+								for dialog in dialog!(list) {
+									dialog.message(format!("Try again in {} seconds", seconds));
+								}
+							}
 
-											continue;
-										}
-									}
+							Request::Prompt(message, echo) => {
+								checking = false;
+								prompt   = true;
 
-									window.lock().unwrap();
-									window.blank();
+								for saver in saver!(list) {
+									saver.prompt(message.clone(), echo).unwrap();
+								}
+
+								for dialog in dialog!(list) {
+									dialog.message(message.clone());
 								}
 							}
 
-							Request::Lock => {
+							Request::Info(message) => {
 								for saver in saver!(list) {
-									saver.lock().unwrap();
+									saver.info(message.clone()).unwrap();
+								}
+
+								for dialog in dialog!(list) {
+									dialog.message(message.clone());
+								}
+							}
+
+							Request::Error(message) => {
+								for saver in saver!(list) {
+									saver.error(message.clone()).unwrap();
+								}
+
+								for dialog in dialog!(list) {
+									dialog.message(message.clone());
+								}
+							}
+
+							Request::Refused(reason) => {
+								for saver in saver!(list) {
+									saver.refused(reason).unwrap();
+								}
+
+								for dialog in dialog!(list) {
+									dialog.message(match reason {
+										Reason::AccountExpired  => "The account has expired",
+										Reason::PasswordExpired => "The password has expired",
+									});
 								}
 							}
 
 							Request::Auth(state) => {
+								prompt   = false;
 								checking = false;
 
+								if state {
+									delay = None;
+								}
+
 								for saver in saver!(list) {
 									saver.password(if state { Password::Success } else { Password::Failure }).unwrap();
 								}
+
+								for dialog in dialog!(list) {
+									dialog.password(if state { Password::Success } else { Password::Failure });
+								}
+							}
+
+							Request::AuthTimeout => {
+								checking = delay.is_some();
+								prompt   = false;
+
+								for saver in saver!(list) {
+									saver.timeout().unwrap();
+								}
+
+								for dialog in dialog!(list) {
+									dialog.timeout();
+								}
 							}
 
 							Request::Stop => {
+								active = false;
+								locked = false;
+								prompt = false;
+								cycling.clear();
+
+								sender.send(Response::Timeout(timer::Timeout::Cancel { id: CYCLE })).unwrap();
+								dialogs.clear();
+
 								for (&id, window) in &mut windows {
 									if let Some(saver) = saver!(? id) {
 										sender.send(Response::Timeout(timer::Timeout::Set {
@@ -256,6 +713,22 @@ impl Locker {
 									}
 								}
 							}
+
+							Request::Reload => {
+								crashes.clear();
+								blacklist.clear();
+
+								// Grab the lock keys again if they changed.
+								let current = lock_keys(&config);
+
+								if current != locks {
+									ungrab_locks(&display, &grabs);
+									grabs = grab_locks(&display, &keyboard, &current);
+									locks = current;
+
+									display.flush();
+								}
+							}
 						}
 					},
 
@@ -263,6 +736,11 @@ impl Locker {
 					event = s_receiver.recv() => {
 						let (id, event) = event.unwrap();
 
+						// Ignore savers whose monitor went away.
+						if !savers.contains_key(&id) || !windows.contains_key(&id) {
+							continue;
+						}
+
 						match event {
 							saver::Response::Forward(api::Response::Initialized) => {
 								saver!(id).start().unwrap();
@@ -289,20 +767,44 @@ impl Locker {
 								}
 							}
 
-							saver::Response::Exit(..) => {
-								if saver!(id).was_stopped() {
-									window!(id).unlock().unwrap();
+							saver::Response::Exit(status) => {
+								let saver = saver!(remove id).unwrap();
+
+								if saver.was_stopped() {
+									// Replace the saver without unlocking the window.
+									if cycling.remove(&id) {
+										window!(id).blank();
+										saver!(start window!(id));
 
-									if savers.len() == 1 {
-										sender.send(Response::Stopped).unwrap();
+										if locked {
+											if let Some(saver) = saver!(? id) {
+												saver.lock().unwrap();
+											}
+										}
+									}
+									else {
+										window!(id).unlock().unwrap();
+
+										if savers.is_empty() {
+											sender.send(Response::Stopped).unwrap();
+										}
 									}
 								}
 								else {
-									window!(id).lock().unwrap();
-									window!(id).blank();
+									sender.send(Response::Timeout(timer::Timeout::Cancel { id: id as u64 })).unwrap();
+									failed!(saver.name().to_owned(), format!("{}", *status), saver.stderr());
+
+									// Try the next saver, or blank the window if none is left.
+									saver!(start window!(id));
+
+									if locked {
+										if let Some(saver) = saver!(? id) {
+											saver.lock().unwrap();
+										}
+									}
 								}
 
-								saver!(remove id);
+								dialog!(update);
 							}
 						}
 					},
@@ -317,7 +819,7 @@ impl Locker {
 								let event = xcb::cast_event(&event): &xcb::randr::ScreenChangeNotifyEvent;
 
 								for window in window!(list) {
-									if window.root() == event.root() {
+									if window.root() == event.root() && window.monitor().is_none() {
 										window.resize(event.width() as u32, event.height() as u32);
 
 										if let Some(saver) = saver!(? window.id()) {
@@ -327,9 +829,31 @@ impl Locker {
 								}
 							}
 
+							// Handle monitors being added, removed or changed.
+							e if display.randr().map_or(false, |rr| e == rr.first_event() + xcb::randr::NOTIFY) => {
+								let event = xcb::cast_event(&event): &xcb::randr::NotifyEvent;
+
+								match event.sub_code() as u32 {
+									xcb::randr::NOTIFY_CRTC_CHANGE | xcb::randr::NOTIFY_OUTPUT_CHANGE =>
+										monitors!(),
+
+									_ => ()
+								}
+							}
+
 							// Handle keyboard events.
 							e if keyboard.owns_event(e) => {
-								keyboard.handle(&event);
+								if keyboard.handle(&event) {
+									let status = keyboard.status();
+
+									for saver in saver!(list) {
+										saver.keyboard(status.clone()).unwrap();
+									}
+
+									for dialog in dialog!(list) {
+										dialog.keyboard(status.clone());
+									}
+								}
 							}
 
 							// Handle keyboard input.
@@ -337,63 +861,158 @@ impl Locker {
 							// Note we only act on key presses because `Xutf8LookupString`
 							// only generates strings from `KeyPress` events.
 							xcb::KEY_PRESS => {
+								let event   = xcb::cast_event(&event): &xcb::KeyPressEvent;
+								let pressed = Binding::new(keyboard.symbol(event.detail() as xkb::Keycode),
+									keyboard.is_active(xkb::MOD_NAME_CTRL),
+									keyboard.is_active(xkb::MOD_NAME_ALT),
+									keyboard.is_active(xkb::MOD_NAME_SHIFT),
+									keyboard.is_active(xkb::MOD_NAME_LOGO));
+
+								// Lock keys are grabbed on the root windows, they're not
+								// activity or else a running saver would be stopped.
+								if locks.iter().any(|binding| binding.matches(&pressed)) {
+									sender.send(Response::Lock).unwrap();
+									continue;
+								}
+
 								sender.send(Response::Activity).unwrap();
 
+								// Accept input again once the delay expired.
+								if delay.map_or(false, |until| Instant::now() >= until) {
+									checking = false;
+									delay    = None;
+								}
+
+								// Whitelisted keys run their command and never reach the
+								// password, the command doesn't run while the password is
+								// being checked, only while waiting after a failure.
+								if let Some(command) = config.locker().passthrough(&pressed) {
+									let now      = Instant::now();
+									let interval = Duration::from_millis(config.locker().passthrough_interval());
+
+									if windows.contains_key(&event.event()) && !(checking && delay.is_none()) && passed.get(&pressed).map_or(true, |&last| now - last >= interval) {
+										passed.insert(pressed, now);
+										passthrough(&command);
+									}
+
+									continue;
+								}
+
 								// Ignore keyboard input while checking authentication.
 								if checking {
 									continue;
 								}
 
-								let event = xcb::cast_event(&event): &xcb::KeyPressEvent;
 								if let Some(_window) = windows.values().find(|w| w.id() == event.event()) {
-									match keyboard.symbol(event.detail() as xkb::Keycode) {
-										// Delete a character.
-										key::KEY_BackSpace => {
-											if !password.is_empty() {
+									match config.locker().key(&pressed) {
+										// Delete a character, dropping any compose sequence in
+										// progress.
+										Some(Action::Delete) => {
+											keyboard.reset();
+
+											if password.pop().is_some() {
+												for saver in saver!(list) {
+													saver.password(Password::Delete).unwrap();
+												}
+
+												for dialog in dialog!(list) {
+													dialog.password(Password::Delete);
+												}
+											}
+										}
+
+										// Delete the trailing whitespace and the word before it.
+										Some(Action::DeleteWord) => {
+											keyboard.reset();
+
+											let mut word = false;
+
+											while let Some(ch) = password.as_str().chars().rev().next() {
+												if ch.is_whitespace() && word {
+													break;
+												}
+
+												word = word || !ch.is_whitespace();
 												password.pop();
 
 												for saver in saver!(list) {
 													saver.password(Password::Delete).unwrap();
 												}
+
+												for dialog in dialog!(list) {
+													dialog.password(Password::Delete);
+												}
 											}
 										}
 
 										// Clear the password.
-										key::KEY_Escape => {
+										Some(Action::Clear) => {
+											keyboard.reset();
+
 											if !password.is_empty() {
 												password.clear();
 
 												for saver in saver!(list) {
 													saver.password(Password::Reset).unwrap();
 												}
+
+												for dialog in dialog!(list) {
+													dialog.password(Password::Reset);
+												}
 											}
 										}
 
-										// Check authentication.
-										key::KEY_Return => {
+										// Check authentication, as the owner or as someone else.
+										Some(action @ Action::Check) | Some(action @ Action::Override) => {
+											keyboard.reset();
+
 											for saver in saver!(list) {
 												saver.password(Password::Check).unwrap();
 											}
 
-											sender.send(Response::Password(password)).unwrap();
+											for dialog in dialog!(list) {
+												dialog.password(Password::Check);
+											}
+
+											// Answer the pending prompt or start a new attempt.
+											if prompt {
+												sender.send(Response::Answer(password)).unwrap();
+											}
+											else if action == Action::Override {
+												sender.send(Response::Override(password)).unwrap();
+											}
+											else {
+												sender.send(Response::Password(password)).unwrap();
+											}
 
 											checking = true;
-											password = String::new();
+											prompt   = false;
+											password = Secret::new();
 										}
 
-										_ => {
-											// Limit the maximum password length so keeping a button
-											// pressed is not going to OOM us in the extremely long
-											// run.
-											if password.len() <= 255 {
-												for ch in keyboard.string(event.detail() as xkb::Keycode).chars() {
-													password.push(ch);
-
-													for saver in saver!(list) {
-														saver.password(Password::Insert).unwrap();
-													}
+										Some(Action::Ignore) => (),
+
+										None => {
+											let mut typed = keyboard.string(event.detail() as xkb::Keycode);
+
+											// The secret has a fixed capacity, anything past it is
+											// dropped, and control characters from unbound keys are
+											// never part of a password.
+											for ch in typed.chars().filter(|ch| !ch.is_control()) {
+												if !password.push(ch) {
+													break;
+												}
+
+												for saver in saver!(list) {
+													saver.password(Password::Insert).unwrap();
+												}
+
+												for dialog in dialog!(list) {
+													dialog.password(Password::Insert);
 												}
 											}
+
+											secret::wipe(unsafe { typed.as_mut_vec() });
 										}
 									}
 								}
@@ -403,7 +1022,8 @@ impl Locker {
 								sender.send(Response::Activity).unwrap();
 							}
 
-							// Handle mouse button presses.
+							// Handle mouse button presses, they only reach the saver so
+							// pasting the selection with a middle click does nothing.
 							xcb::BUTTON_PRESS | xcb::BUTTON_RELEASE => {
 								sender.send(Response::Activity).unwrap();
 
@@ -436,6 +1056,17 @@ impl Locker {
 								}
 							}
 
+							// Redraw the dialog when the window is exposed.
+							xcb::EXPOSE => {
+								let event = xcb::cast_event(&event): &xcb::ExposeEvent;
+
+								if event.count() == 0 {
+									if let Some(dialog) = dialogs.get(&event.window()) {
+										dialog.draw();
+									}
+								}
+							}
+
 							// On window changes, try to observe the window.
 							xcb::MAP_NOTIFY | xcb::CONFIGURE_NOTIFY => {
 								let event = xcb::cast_event(&event): &xcb::MapNotifyEvent;
@@ -452,9 +1083,15 @@ impl Locker {
 		Ok(Locker {
 			receiver: i_receiver,
 			sender:   i_sender,
+			display:  name,
 		})
 	}
 
+	/// The name of the display being locked.
+	pub fn display(&self) -> &str {
+		&self.display
+	}
+
 	pub fn sanitize(&self) -> Result<(), SendError<Request>> {
 		self.sender.send(Request::Sanitize)
 	}
@@ -471,14 +1108,46 @@ impl Locker {
 		self.sender.send(Request::Lock)
 	}
 
+	pub fn cycle(&self) -> Result<(), SendError<Request>> {
+		self.sender.send(Request::Cycle)
+	}
+
 	pub fn auth(&self, value: bool) -> Result<(), SendError<Request>> {
 		self.sender.send(Request::Auth(value))
 	}
 
+	pub fn auth_timeout(&self) -> Result<(), SendError<Request>> {
+		self.sender.send(Request::AuthTimeout)
+	}
+
+	pub fn delay(&self, seconds: u64) -> Result<(), SendError<Request>> {
+		self.sender.send(Request::Delay(seconds))
+	}
+
+	pub fn prompt<S: Into<String>>(&self, message: S, echo: bool) -> Result<(), SendError<Request>> {
+		self.sender.send(Request::Prompt(message.into(), echo))
+	}
+
+	pub fn info<S: Into<String>>(&self, message: S) -> Result<(), SendError<Request>> {
+		self.sender.send(Request::Info(message.into()))
+	}
+
+	pub fn error<S: Into<String>>(&self, message: S) -> Result<(), SendError<Request>> {
+		self.sender.send(Request::Error(message.into()))
+	}
+
+	pub fn refused(&self, reason: Reason) -> Result<(), SendError<Request>> {
+		self.sender.send(Request::Refused(reason))
+	}
+
 	pub fn stop(&self) -> Result<(), SendError<Request>> {
 		self.sender.send(Request::Stop)
 	}
 
+	pub fn reload(&self) -> Result<(), SendError<Request>> {
+		self.sender.send(Request::Reload)
+	}
+
 	pub fn power(&self, value: bool) -> Result<(), SendError<Request>> {
 		self.sender.send(Request::Power(value))
 	}
@@ -492,6 +1161,117 @@ impl Locker {
 	}
 }
 
+/// Get the bindings locking the screen, the XF86ScreenSaver key always does.
+fn lock_keys(config: &Config) -> Vec<Binding> {
+	let mut locks = vec![Binding::new(key::KEY_XF86ScreenSaver, false, false, false, false)];
+
+	if let Some(binding) = config.locker().lock_key() {
+		locks.push(binding);
+	}
+
+	locks
+}
+
+/// Grab the lock keys on every root window, returning the grabs to release
+/// them later.
+fn grab_locks(display: &Display, keyboard: &Keyboard, locks: &[Binding]) -> Vec<(u32, u8, u16)> {
+	let mut grabs = Vec::new();
+
+	for binding in locks {
+		let codes = keyboard.codes(binding.symbol);
+		let mut taken = false;
+
+		if codes.is_empty() {
+			debug!("locker: no key code for lock key {:?}", binding);
+		}
+
+		for code in codes {
+			for screen in display.get_setup().roots() {
+				if !display.grab_key(screen.root(), code as u8, mask(binding)) {
+					taken = true;
+				}
+
+				grabs.push((screen.root(), code as u8, mask(binding)));
+			}
+		}
+
+		if taken {
+			warn!("locker: lock key {:?} is already grabbed by another client", binding);
+		}
+	}
+
+	grabs
+}
+
+/// Release the grabs of the lock keys.
+fn ungrab_locks(display: &Display, grabs: &[(u32, u8, u16)]) {
+	for &(root, code, modifiers) in grabs {
+		display.ungrab_key(root, code, modifiers);
+	}
+}
+
+/// Get the X11 modifier mask of the binding.
+fn mask(binding: &Binding) -> u16 {
+	let mut mask = 0;
+
+	if binding.control {
+		mask |= xcb::MOD_MASK_CONTROL;
+	}
+
+	if binding.alt {
+		mask |= xcb::MOD_MASK_1;
+	}
+
+	if binding.shift {
+		mask |= xcb::MOD_MASK_SHIFT;
+	}
+
+	if binding.logo {
+		mask |= xcb::MOD_MASK_4;
+	}
+
+	mask as u16
+}
+
+/// Pick the locked window that should hold the grabs out of `(id, root,
+/// monitor)` entries, preferring the one under the pointer, then one on a
+/// primary monitor, then one on the default screen.
+fn holder(windows: &[(u32, u32, Option<Monitor>)], pointer: Option<(u32, i16, i16)>, primary: &[u32], default: u32) -> Option<u32> {
+	if let Some((root, x, y)) = pointer {
+		if let Some(&(id, _, _)) = windows.iter().find(|&&(_, r, m)| r == root && m.map_or(true, |m| m.contains(x, y))) {
+			return Some(id);
+		}
+	}
+
+	if let Some(&(id, _, _)) = windows.iter().find(|&&(_, _, m)| m.map_or(false, |m| primary.contains(&m.crtc))) {
+		return Some(id);
+	}
+
+	windows.iter().find(|&&(_, r, _)| r == default).or_else(|| windows.first())
+		.map(|&(id, _, _)| id)
+}
+
+/// Run the command of a passthrough key, waiting for it in the background.
+fn passthrough(command: &str) {
+	debug!("locker: running passthrough command: {}", command);
+
+	let child = Command::new("sh").arg("-c").arg(command)
+		.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null())
+		.spawn();
+
+	match child {
+		Ok(mut child) => {
+			thread::spawn(move || {
+				let _ = child.wait();
+			});
+		}
+
+		Err(err) => {
+			warn!("locker: could not run passthrough command: {:?}", err);
+		}
+	}
+}
+
 impl Deref for Locker {
 	type Target = Receiver<Response>;
 
@@ -499,3 +1279,107 @@ impl Deref for Locker {
 		&self.receiver
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use std::env;
+	use std::thread;
+	use std::sync::Arc;
+	use std::time::Duration;
+	use std::process::{Command, Child, Stdio};
+
+	use xcb;
+
+	use config::Config;
+	use super::holder;
+	use super::super::{Display, Monitor, Window};
+
+	fn monitor(crtc: u32, x: i16) -> Option<Monitor> {
+		Some(Monitor { crtc: crtc, x: x, y: 0, width: 1024, height: 768 })
+	}
+
+	#[test]
+	fn pointer() {
+		let windows = [(1, 10, monitor(100, 0)), (2, 10, monitor(101, 1024)), (3, 20, None)];
+
+		assert_eq!(Some(2), holder(&windows, Some((10, 2000, 10)), &[100], 10));
+		assert_eq!(Some(1), holder(&windows, Some((10, 10, 10)), &[101], 10));
+		assert_eq!(Some(3), holder(&windows, Some((20, 10, 10)), &[100], 10));
+	}
+
+	#[test]
+	fn primary() {
+		let windows = [(1, 10, monitor(100, 0)), (2, 10, monitor(101, 1024))];
+
+		assert_eq!(Some(2), holder(&windows, None, &[101], 10));
+		assert_eq!(Some(2), holder(&windows, Some((10, 5000, 10)), &[101], 10));
+	}
+
+	#[test]
+	fn fallback() {
+		let windows = [(1, 20, None), (2, 10, monitor(100, 0))];
+
+		assert_eq!(Some(2), holder(&windows, None, &[], 10));
+		assert_eq!(Some(1), holder(&windows, None, &[], 30));
+		assert_eq!(None, holder(&[], Some((10, 0, 0)), &[100], 10));
+	}
+
+	/// Start a virtual X server, returning `None` if `Xvfb` isn't available.
+	fn xvfb() -> Option<(Child, Arc<Display>)> {
+		let name  = format!(":{}", 100 + unsafe { ::libc::getpid() } % 1000);
+		let child = Command::new("Xvfb").arg(&name)
+			.arg("-screen").arg("0").arg("2048x768x24").arg("+extension").arg("RANDR")
+			.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null()).spawn();
+
+		let mut child = if let Ok(child) = child { child } else { return None };
+		env::set_var("DISPLAY", &name);
+
+		for _ in 0 .. 50 {
+			if let Ok(display) = Display::open(Config::default().locker()) {
+				return Some((child, display));
+			}
+
+			thread::sleep(Duration::from_millis(100));
+		}
+
+		let _ = child.kill();
+		None
+	}
+
+	#[test]
+	fn regrab() {
+		let (mut server, display) = if let Some(value) = xvfb() { value } else { return };
+		let root                  = display.get_setup().roots().nth(0).unwrap().root();
+		let monitors              = display.monitors(0);
+
+		let mut windows = if monitors.is_empty() {
+			vec![Window::create(display.clone(), 0, 0, None).unwrap()]
+		}
+		else {
+			monitors.iter().map(|&m| Window::create(display.clone(), 0, 0, Some(m)).unwrap()).collect()
+		};
+
+		for window in &mut windows {
+			window.lock().unwrap();
+		}
+
+		xcb::warp_pointer(&display, xcb::WINDOW_NONE, root, 0, 0, 0, 0, 1500, 10);
+		display.flush();
+
+		let pointer = display.pointer();
+		assert_eq!(Some((root, 1500, 10)), pointer);
+
+		let candidates = windows.iter().map(|w| (w.id(), w.root(), w.monitor())).collect::<Vec<_>>();
+		let primary    = display.primary(0).into_iter().collect::<Vec<_>>();
+		let id         = holder(&candidates, pointer, &primary, root).unwrap();
+		let window     = windows.iter_mut().find(|w| w.id() == id).unwrap();
+
+		assert!(window.monitor().map_or(true, |m| m.contains(1500, 10)));
+
+		window.regrab();
+		assert!(window.has_keyboard());
+		assert!(window.has_pointer());
+
+		let _ = server.kill();
+	}
+}
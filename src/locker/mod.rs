@@ -19,7 +19,10 @@ mod locker;
 pub use self::locker::{Locker, Request, Response};
 
 mod display;
-pub use self::display::Display;
+pub use self::display::{Display, Monitor};
 
 mod window;
 pub use self::window::Window;
+
+mod dialog;
+pub use self::dialog::Dialog;
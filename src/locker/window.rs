@@ -23,12 +23,13 @@ use std::ops::Deref;
 use xcb;
 
 use error;
-use super::Display;
+use super::{Display, Monitor};
 use platform;
 
 pub struct Window {
 	display: Arc<Display>,
 	window:  platform::Window,
+	monitor: Option<Monitor>,
 	gc:      u32,
 	cursor:  u32,
 
@@ -44,14 +45,19 @@ pub enum Grab {
 }
 
 impl Window {
-	pub fn create(display: Arc<Display>, index: i32) -> error::Result<Window> {
-		let screen = display.get_setup().roots().nth(display.screen() as usize).unwrap();
-		let window = platform::Window::create((**display).clone(), display.screen(),
-			screen.width_in_pixels() as u32, screen.height_in_pixels() as u32)?;
+	/// Create a window covering the given monitor, or the whole screen.
+	pub fn create(display: Arc<Display>, screen: i32, index: i32, monitor: Option<Monitor>) -> error::Result<Window> {
+		let root   = display.get_setup().roots().nth(screen as usize).unwrap();
+		let window = platform::Window::create((**display).clone(), screen,
+			root.width_in_pixels() as u32, root.height_in_pixels() as u32)?;
+
+		if let Some(monitor) = monitor {
+			window.place(monitor.x as i32, monitor.y as i32, monitor.width as u32, monitor.height as u32);
+		}
 
 		let cursor = {
 			let pixmap = display.generate_id();
-			xcb::create_pixmap(&display, 1, pixmap, screen.root(), 1, 1);
+			xcb::create_pixmap(&display, 1, pixmap, root.root(), 1, 1);
 
 			let cursor = display.generate_id();
 			xcb::create_cursor(&display, cursor, pixmap, pixmap, 0, 0, 0, 0, 0, 0, 1, 1);
@@ -69,13 +75,14 @@ impl Window {
 			xcb::ATOM_CARDINAL, 32, &[index]);
 
 		let gc = display.generate_id();
-		xcb::create_gc(&display, gc, window.id(), &[(xcb::GC_FOREGROUND, screen.black_pixel())]);
+		xcb::create_gc(&display, gc, window.id(), &[(xcb::GC_FOREGROUND, root.black_pixel())]);
 
 		display.flush();
 
 		Ok(Window {
 			display: display.clone(),
 			window:  window,
+			monitor: monitor,
 			gc:      gc,
 			cursor:  cursor,
 
@@ -85,6 +92,17 @@ impl Window {
 		})
 	}
 
+	/// Get the monitor the window is covering, `None` if it's the whole screen.
+	pub fn monitor(&self) -> Option<Monitor> {
+		self.monitor
+	}
+
+	/// Move the window to cover the given monitor.
+	pub fn cover(&mut self, monitor: Monitor) {
+		self.window.place(monitor.x as i32, monitor.y as i32, monitor.width as u32, monitor.height as u32);
+		self.monitor = Some(monitor);
+	}
+
 	/// Check if the window is locked.
 	pub fn is_locked(&self) -> bool {
 		self.locked
@@ -120,6 +138,21 @@ impl Window {
 		}
 	}
 
+	/// Grab the keyboard and pointer again, used when the window holding the
+	/// grabs went away.
+	pub fn regrab(&mut self) {
+		if !self.locked {
+			return;
+		}
+
+		self.keyboard = self.try_grab(Grab::Keyboard, 500).is_ok();
+		self.pointer  = self.try_grab(Grab::Pointer, 500).is_ok();
+
+		if !self.keyboard {
+			warn!("could not grab keyboard after monitor change");
+		}
+	}
+
 	/// Grab the given input.
 	pub fn grab(&self, grab: Grab) -> error::Result<()> {
 		let result = match grab {
@@ -35,6 +35,9 @@ extern crate dbus;
 #[cfg(feature = "auth-pam")]
 extern crate pam_sys as pam;
 
+#[cfg(feature = "auth-totp")]
+extern crate crypto;
+
 extern crate libc;
 extern crate xcb;
 extern crate xcb_util;
@@ -48,6 +51,11 @@ mod error;
 mod config;
 use config::Config;
 
+mod wipe;
+mod secret;
+
+#[cfg(feature = "auth-helper")]
+mod service;
 mod platform;
 mod saver;
 
@@ -66,6 +74,9 @@ use interface::Interface;
 mod timer;
 use timer::Timer;
 
+mod audit;
+use audit::Audit;
+
 fn main() {
 	env_logger::init().unwrap();
 
@@ -128,6 +139,12 @@ fn main() {
 				.help("The path to the configuration file.")
 				.takes_value(true)));
 
+	#[cfg(feature = "auth-internal")]
+	{
+		app = app.subcommand(SubCommand::with_name("hash-password")
+			.about("Hash a password for the internal authorization module."));
+	}
+
 	let matches = app.clone().get_matches();
 
 	exit(match matches.subcommand() {
@@ -167,6 +184,10 @@ fn main() {
 		("daemon", Some(submatches)) =>
 			daemon(submatches),
 
+		#[cfg(feature = "auth-internal")]
+		("hash-password", Some(submatches)) =>
+			hash_password(submatches),
+
 		_ =>
 			app.print_help().map_err(|e| e.into())
 	});
@@ -196,6 +217,9 @@ fn exit<T>(value: error::Result<T>) -> T {
 			Error::Parse =>
 				error!(1, "The configuration file has a syntax error."),
 
+			Error::Config(ref message) =>
+				error!(1, "The configuration file is invalid: {}", message),
+
 			Error::DBus(error::DBus::AlreadyRegistered) =>
 				error!(10, "Another screen saver is currently running."),
 
@@ -223,6 +247,80 @@ fn preview(matches: &ArgMatches) -> error::Result<()> {
 	Ok(())
 }
 
+#[cfg(feature = "auth-internal")]
+fn hash_password(_matches: &ArgMatches) -> error::Result<()> {
+	use std::io::{self, Write};
+	use std::mem;
+	use std::str;
+	use secret::{self, Secret};
+
+	/// Read a line from stdin, disabling echo if it's a terminal.
+	fn read(prompt: &str) -> error::Result<Secret> {
+		// Read a byte at a time bypassing the buffered `stdin`, so no copies are
+		// left behind in buffers we don't control.
+		let mut line   = [0u8; secret::CAPACITY];
+		let mut length = 0;
+
+		unsafe {
+			let tty = libc::isatty(libc::STDIN_FILENO) == 1;
+			let mut previous = mem::zeroed(): libc::termios;
+
+			if tty {
+				write!(&mut io::stderr(), "{}", prompt)?;
+
+				libc::tcgetattr(libc::STDIN_FILENO, &mut previous);
+				let mut current = previous;
+				current.c_lflag &= !libc::ECHO;
+				libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &current);
+			}
+
+			let mut result = Ok(());
+
+			loop {
+				let mut byte = 0u8;
+
+				match libc::read(libc::STDIN_FILENO, &mut byte as *mut u8 as *mut _, 1) {
+					1 if byte != b'\n' => {
+						if length < secret::CAPACITY - 1 {
+							line[length] = byte;
+							length += 1;
+						}
+					}
+
+					0 | 1 =>
+						break,
+
+					_ => {
+						result = Err(io::Error::last_os_error());
+						break;
+					}
+				}
+			}
+
+			let password = str::from_utf8(&line[.. length]).map(Secret::from);
+			secret::wipe(&mut line);
+
+			if tty {
+				libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &previous);
+				writeln!(&mut io::stderr(), "")?;
+			}
+
+			result?;
+			password.map_err(|_| error::Error::Message("The password is not valid UTF-8.".into()))
+		}
+	}
+
+	let password = read("Password: ")?;
+
+	if !password.matches(&read("Confirm password: ")?) {
+		return Err(error::Error::Message("The passwords do not match.".into()));
+	}
+
+	println!("{}", auth::internal::hash(&password)?);
+
+	Ok(())
+}
+
 fn daemon(matches: &ArgMatches) -> error::Result<()> {
 	use std::time::{Instant, SystemTime};
 	use std::collections::HashSet;
@@ -253,15 +351,25 @@ fn daemon(matches: &ArgMatches) -> error::Result<()> {
 
 	let config    = Config::load(matches.value_of("config"))?;
 	let timer     = Timer::spawn(config.timer())?;
-	let auth      = Auth::spawn(config.auth())?;
 	let interface = Interface::spawn(config.interface())?;
 	let locker    = Locker::spawn(config.clone())?;
+	let audit     = Audit::new(config.audit());
+
+	// The authentication methods need the name of the display the locker
+	// connected to.
+	let auth = Auth::spawn(config.auth(), locker.display())?;
 
 	let mut locked    = None: Option<Instant>;
 	let mut started   = None: Option<Instant>;
 	let mut blanked   = None: Option<Instant>;
 	let mut suspended = None: Option<SystemTime>;
 
+	// Failed attempts since the screen was locked.
+	let mut failures = 0;
+
+	// Whether an authentication attempt is running.
+	let mut checking = false;
+
 	let mut inhibitors = HashSet::new();
 	let mut throttlers = HashSet::new();
 	let mut suspenders = HashSet::new();
@@ -309,8 +417,12 @@ fn daemon(matches: &ArgMatches) -> error::Result<()> {
 			timer.started().unwrap();
 		);
 
-		(lock) => (
-			locked = Some(Instant::now());
+		(lock $reason:expr) => (
+			locked   = Some(Instant::now());
+			failures = 0;
+
+			audit.lock($reason);
+			auth.start().unwrap();
 
 			locker.lock().unwrap();
 			timer.locked().unwrap();
@@ -324,24 +436,41 @@ fn daemon(matches: &ArgMatches) -> error::Result<()> {
 			started = None;
 			locked  = None;
 
+			auth.stop().unwrap();
+
 			interface.signal(interface::Signal::Active(false)).unwrap();
 			timer.stopped().unwrap();
 		);
 
 		(auth < $value:expr) => (
+			checking = true;
 			interface.signal(interface::Signal::AuthenticationRequest(true)).unwrap();
 			auth.authenticate($value).unwrap();
 		);
 
+		(auth over $value:expr) => (
+			checking = true;
+			interface.signal(interface::Signal::AuthenticationRequest(true)).unwrap();
+			auth.over($value).unwrap();
+		);
+
 		(auth success) => (
+			checking = false;
 			locker.auth(true).unwrap();
 			interface.signal(interface::Signal::AuthenticationRequest(false)).unwrap();
 		);
 
 		(auth failure) => (
+			checking = false;
 			locker.auth(false).unwrap();
 			interface.signal(interface::Signal::AuthenticationRequest(false)).unwrap();
 		);
+
+		(auth timeout) => (
+			checking = false;
+			locker.auth_timeout().unwrap();
+			interface.signal(interface::Signal::AuthenticationRequest(false)).unwrap();
+		);
 	}
 
 	// XXX: select! is icky, this works around shadowing the outer name
@@ -390,9 +519,38 @@ fn daemon(matches: &ArgMatches) -> error::Result<()> {
 						act!(auth < pwd);
 					}
 
+					// Try authorization as someone else.
+					locker::Response::Override(pwd) => {
+						act!(auth over pwd);
+					}
+
+					// Answer a prompt from the authentication.
+					locker::Response::Answer(answer) => {
+						auth.answer(answer).unwrap();
+					}
+
+					// The lock key was pressed.
+					locker::Response::Lock => {
+						if started.is_none() {
+							act!(start);
+						}
+
+						if locked.is_none() {
+							act!(lock audit::Lock::Manual);
+						}
+					}
+
 					locker::Response::Stopped => {
 						act!(stopped);
 					}
+
+					locker::Response::Failed { name, reason, stderr } => {
+						interface.signal(interface::Signal::SaverFailed {
+							name:   name,
+							reason: reason,
+							stderr: stderr.join("\n"),
+						}).unwrap();
+					}
 				}
 			},
 
@@ -402,15 +560,75 @@ fn daemon(matches: &ArgMatches) -> error::Result<()> {
 					auth::Response::Success => {
 						info!("authorization: success");
 
+						if locked.is_some() {
+							if failures > 0 {
+								info!("authorization: {} failed attempts while locked", failures);
+							}
+
+							audit.unlock(failures);
+							interface.signal(interface::Signal::Unlocked(failures)).unwrap();
+						}
+
 						act!(auth success);
 						act!(stop);
 					}
 
-					auth::Response::Failure => {
+					auth::Response::Failure(user) => {
 						info!("authorization: failure");
 
+						failures += 1;
+						audit.failure(user.as_ref().map(|u| u.as_ref()));
+
 						act!(auth failure);
 					}
+
+					// Timing out counts as a failure in the authentication too.
+					auth::Response::Timeout => {
+						info!("authorization: timeout");
+
+						failures += 1;
+						audit.timeout();
+
+						act!(auth timeout);
+					}
+
+					auth::Response::Delay(seconds) => {
+						info!("authorization: delayed for {} seconds", seconds);
+
+						locker.delay(seconds).unwrap();
+
+						// The attempt was refused without being checked.
+						if checking {
+							checking = false;
+							interface.signal(interface::Signal::AuthenticationRequest(false)).unwrap();
+						}
+					}
+
+					auth::Response::Prompt { message, echo } => {
+						locker.prompt(message, echo).unwrap();
+					}
+
+					auth::Response::Info(message) => {
+						locker.info(message).unwrap();
+					}
+
+					auth::Response::Error(message) => {
+						locker.error(message).unwrap();
+					}
+
+					auth::Response::Override(user) => {
+						warn!("authorization: unlocked by {}", user);
+
+						audit.over(&user);
+
+						interface.signal(interface::Signal::Override(user)).unwrap();
+					}
+
+					auth::Response::Refused(reason) => {
+						info!("authorization: refused, {:?}", reason);
+
+						locker.refused(reason).unwrap();
+					}
 				}
 			},
 
@@ -418,9 +636,11 @@ fn daemon(matches: &ArgMatches) -> error::Result<()> {
 			event = s.recv() => {
 				match event.unwrap() {
 					interface::Request::Reload(source) => {
-						config.reset();
 						interface.response(interface::Response::Reload(
 							config.reload(source).is_ok())).unwrap();
+
+						// Give blacklisted savers another chance.
+						locker.reload().unwrap();
 					}
 
 					interface::Request::Lock => {
@@ -429,12 +649,15 @@ fn daemon(matches: &ArgMatches) -> error::Result<()> {
 						}
 
 						if locked.is_none() {
-							act!(lock);
+							act!(lock audit::Lock::Manual);
 						}
 					}
 
-					// TODO: Implement cycling.
-					interface::Request::Cycle => (),
+					interface::Request::Cycle => {
+						if started.is_some() {
+							locker.cycle().unwrap();
+						}
+					}
 
 					interface::Request::SimulateUserActivity => {
 						locker.activity().unwrap();
@@ -537,7 +760,7 @@ fn daemon(matches: &ArgMatches) -> error::Result<()> {
 
 								config::OnSuspend::Lock => {
 									act!(start);
-									act!(lock);
+									act!(lock audit::Lock::Suspend);
 								}
 							}
 						}
@@ -597,7 +820,7 @@ fn daemon(matches: &ArgMatches) -> error::Result<()> {
 					}
 
 					timer::Response::Lock => {
-						act!(lock);
+						act!(lock audit::Lock::Idle);
 					}
 
 					timer::Response::Blank => {
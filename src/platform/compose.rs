@@ -0,0 +1,176 @@
+// Copyleft (ↄ) meh. <meh@schizofreni.co> | http://meh.schizofreni.co
+//
+// This file is part of screenruster.
+//
+// screenruster is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// screenruster is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::env;
+use std::ffi::CString;
+use std::cmp;
+
+use libc::{c_int, c_char, size_t};
+use xkbcommon::xkb;
+
+use secret;
+
+#[allow(non_camel_case_types)]
+enum xkb_compose_table { }
+
+#[allow(non_camel_case_types)]
+enum xkb_compose_state { }
+
+const XKB_COMPOSE_FEED_ACCEPTED: c_int = 1;
+
+const XKB_COMPOSE_COMPOSING: c_int = 1;
+const XKB_COMPOSE_COMPOSED:  c_int = 2;
+const XKB_COMPOSE_CANCELLED: c_int = 3;
+
+#[link(name = "xkbcommon")]
+extern "C" {
+	fn xkb_compose_table_new_from_locale(context: *mut xkb::ffi::xkb_context, locale: *const c_char, flags: c_int) -> *mut xkb_compose_table;
+	fn xkb_compose_table_unref(table: *mut xkb_compose_table);
+
+	fn xkb_compose_state_new(table: *mut xkb_compose_table, flags: c_int) -> *mut xkb_compose_state;
+	fn xkb_compose_state_unref(state: *mut xkb_compose_state);
+	fn xkb_compose_state_feed(state: *mut xkb_compose_state, keysym: xkb::Keysym) -> c_int;
+	fn xkb_compose_state_reset(state: *mut xkb_compose_state);
+	fn xkb_compose_state_get_status(state: *mut xkb_compose_state) -> c_int;
+	fn xkb_compose_state_get_utf8(state: *mut xkb_compose_state, buffer: *mut c_char, size: size_t) -> c_int;
+	fn xkb_compose_state_get_one_sym(state: *mut xkb_compose_state) -> xkb::Keysym;
+}
+
+/// Compose and dead key sequences for the user locale.
+pub struct Compose {
+	table: *mut xkb_compose_table,
+	state: *mut xkb_compose_state,
+}
+
+/// The result of feeding a key symbol.
+pub enum Feed {
+	/// The symbol is not part of a sequence and should be used as is.
+	Ignored,
+
+	/// A sequence is in progress.
+	Composing,
+
+	/// A sequence was completed with the given string.
+	Composed(String),
+
+	/// The sequence was cancelled, the symbol should be dropped.
+	Cancelled,
+}
+
+unsafe impl Send for Compose { }
+
+impl Compose {
+	/// Load the compose table for the current locale, `None` if there's none.
+	pub fn new(context: &xkb::Context) -> Option<Compose> {
+		// Same lookup order as `setlocale(LC_CTYPE, "")`.
+		let locale = env::var("LC_ALL").ok().into_iter()
+			.chain(env::var("LC_CTYPE").ok())
+			.chain(env::var("LANG").ok())
+			.find(|value| !value.is_empty())
+			.unwrap_or("C".into());
+
+		let locale = if let Ok(value) = CString::new(locale) {
+			value
+		}
+		else {
+			return None;
+		};
+
+		unsafe {
+			let table = xkb_compose_table_new_from_locale(context.get_raw_ptr(), locale.as_ptr(), 0);
+
+			if table.is_null() {
+				return None;
+			}
+
+			let state = xkb_compose_state_new(table, 0);
+
+			if state.is_null() {
+				xkb_compose_table_unref(table);
+				return None;
+			}
+
+			Some(Compose {
+				table: table,
+				state: state,
+			})
+		}
+	}
+
+	/// Feed a key symbol to the current sequence.
+	pub fn feed(&mut self, symbol: xkb::Keysym) -> Feed {
+		unsafe {
+			if xkb_compose_state_feed(self.state, symbol) != XKB_COMPOSE_FEED_ACCEPTED {
+				return Feed::Ignored;
+			}
+
+			match xkb_compose_state_get_status(self.state) {
+				XKB_COMPOSE_COMPOSING => {
+					Feed::Composing
+				}
+
+				XKB_COMPOSE_COMPOSED => {
+					let mut buffer = [0u8; 64];
+					let mut length = xkb_compose_state_get_utf8(self.state, buffer.as_mut_ptr() as *mut c_char, buffer.len() as size_t);
+
+					// Some sequences only have a key symbol, use its string then, the
+					// length includes the terminating NUL.
+					if length <= 0 {
+						length = xkb::ffi::xkb_keysym_to_utf8(xkb_compose_state_get_one_sym(self.state),
+							buffer.as_mut_ptr() as *mut c_char, buffer.len() as size_t) - 1;
+					}
+
+					let length = cmp::min(cmp::max(length, 0) as usize, buffer.len() - 1);
+					let string = String::from_utf8_lossy(&buffer[.. length]).into_owned();
+
+					// Don't leave the composed characters around.
+					secret::wipe(&mut buffer);
+
+					xkb_compose_state_reset(self.state);
+
+					Feed::Composed(string)
+				}
+
+				XKB_COMPOSE_CANCELLED => {
+					xkb_compose_state_reset(self.state);
+
+					Feed::Cancelled
+				}
+
+				_ => {
+					Feed::Ignored
+				}
+			}
+		}
+	}
+
+	/// Drop any sequence in progress.
+	pub fn reset(&mut self) {
+		unsafe {
+			xkb_compose_state_reset(self.state);
+		}
+	}
+}
+
+impl Drop for Compose {
+	fn drop(&mut self) {
+		unsafe {
+			xkb_compose_state_unref(self.state);
+			xkb_compose_table_unref(self.table);
+		}
+	}
+}
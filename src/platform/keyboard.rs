@@ -22,6 +22,7 @@ use xkbcommon::xkb;
 
 use error;
 use super::Display;
+use super::compose::{Compose, Feed};
 
 /// Keyboard manager and handler.
 ///
@@ -33,6 +34,23 @@ pub struct Keyboard {
 	device:  i32,
 	keymap:  xkb::Keymap,
 	state:   xkb::State,
+	compose: Option<Compose>,
+}
+
+/// The keyboard state relevant to whoever is typing a password.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Status {
+	/// The name of the active layout.
+	pub layout: String,
+
+	/// Whether Caps Lock is on.
+	pub caps: bool,
+
+	/// Whether Num Lock is on.
+	pub num: bool,
+
+	/// The names of the active modifiers.
+	pub modifiers: Vec<String>,
 }
 
 unsafe impl Send for Keyboard { }
@@ -82,6 +100,11 @@ impl Keyboard {
 		let device  = xkb::x11::get_core_keyboard_device_id(&display);
 		let keymap  = xkb::x11::keymap_new_from_device(&context, &display, device, xkb::KEYMAP_COMPILE_NO_FLAGS);
 		let state   = xkb::x11::state_new_from_device(&keymap, &display, device);
+		let compose = Compose::new(&context);
+
+		if compose.is_none() {
+			warn!("no compose table for the current locale, dead keys will not work");
+		}
 
 		Ok(Keyboard {
 			display: display,
@@ -89,6 +112,7 @@ impl Keyboard {
 			device:  device,
 			keymap:  keymap,
 			state:   state,
+			compose: compose,
 		})
 	}
 
@@ -102,8 +126,10 @@ impl Keyboard {
 		event < self.extension().first_event() + xcb::xkb::EXTENSION_DEVICE_NOTIFY
 	}
 
-	/// Handles an X event.
-	pub fn handle(&mut self, event: &xcb::GenericEvent) {
+	/// Handles an X event, returns whether the keyboard status changed.
+	pub fn handle(&mut self, event: &xcb::GenericEvent) -> bool {
+		let previous = self.status();
+
 		match event.response_type() - self.extension().first_event() {
 			xcb::xkb::NEW_KEYBOARD_NOTIFY | xcb::xkb::MAP_NOTIFY => {
 				self.keymap = xkb::x11::keymap_new_from_device(&self.context, &self.display, self.device, xkb::KEYMAP_COMPILE_NO_FLAGS);
@@ -124,6 +150,36 @@ impl Keyboard {
 
 			_ => ()
 		}
+
+		self.status() != previous
+	}
+
+	/// Get the current keyboard status.
+	pub fn status(&self) -> Status {
+		let layout = self.state.serialize_layout(xkb::STATE_LAYOUT_EFFECTIVE);
+
+		Status {
+			layout: self.keymap.layout_get_name(layout).into(),
+			caps:   self.state.led_name_is_active(&xkb::LED_NAME_CAPS),
+			num:    self.state.led_name_is_active(&xkb::LED_NAME_NUM),
+
+			modifiers: (0 .. self.keymap.num_mods())
+				.filter(|&index| self.state.mod_index_is_active(index, xkb::STATE_MODS_EFFECTIVE))
+				.map(|index| self.keymap.mod_get_name(index).into())
+				.collect(),
+		}
+	}
+
+	/// Check if the given modifier is active.
+	pub fn is_active(&self, modifier: &str) -> bool {
+		self.state.mod_name_is_active(&modifier, xkb::STATE_MODS_EFFECTIVE)
+	}
+
+	/// Find the key codes producing the given key symbol in the first layout.
+	pub fn codes(&self, symbol: xkb::Keysym) -> Vec<xkb::Keycode> {
+		(self.keymap.min_keycode() .. self.keymap.max_keycode() + 1)
+			.filter(|&code| (0 .. 4).any(|level| self.keymap.key_get_syms_by_level(code, 0, level).contains(&symbol)))
+			.collect()
 	}
 
 	/// Translate a key code to the key symbol.
@@ -131,8 +187,31 @@ impl Keyboard {
 		self.state.key_get_one_sym(code)
 	}
 
-	/// Translate a key code to an UTF-8 string.
-	pub fn string(&self, code: xkb::Keycode) -> String {
+	/// Translate a key code to an UTF-8 string, going through compose and dead
+	/// key sequences, so the string is empty while a sequence is in progress.
+	pub fn string(&mut self, code: xkb::Keycode) -> String {
+		let symbol = self.symbol(code);
+
+		if let Some(compose) = self.compose.as_mut() {
+			match compose.feed(symbol) {
+				Feed::Ignored =>
+					(),
+
+				Feed::Composing | Feed::Cancelled =>
+					return String::new(),
+
+				Feed::Composed(string) =>
+					return string,
+			}
+		}
+
 		self.state.key_get_utf8(code)
 	}
+
+	/// Drop any compose sequence in progress.
+	pub fn reset(&mut self) {
+		if let Some(compose) = self.compose.as_mut() {
+			compose.reset();
+		}
+	}
 }
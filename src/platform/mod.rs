@@ -21,5 +21,7 @@ pub use self::display::Display;
 mod window;
 pub use self::window::Window;
 
-mod keyboard;
+pub mod keyboard;
 pub use self::keyboard::Keyboard;
+
+mod compose;
@@ -100,6 +100,17 @@ impl Window {
 		self.flush();
 	}
 
+	/// Move and resize the window.
+	pub fn place(&self, x: i32, y: i32, width: u32, height: u32) {
+		xcb::configure_window(&self.display, self.id(), &[
+			(xcb::CONFIG_WINDOW_X as u16, x as u32),
+			(xcb::CONFIG_WINDOW_Y as u16, y as u32),
+			(xcb::CONFIG_WINDOW_WIDTH as u16, width),
+			(xcb::CONFIG_WINDOW_HEIGHT as u16, height)]);
+
+		self.flush();
+	}
+
 	/// Get the dimensions.
 	pub fn dimensions(&self) -> (u32, u32) {
 		if let Ok(reply) = xcb::get_geometry(&self.display, self.id()).get_reply() {
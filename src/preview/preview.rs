@@ -55,6 +55,7 @@ impl Preview {
 
 		saver.config(config.saver().get(name)).unwrap();
 		saver.target(display.name(), window.screen(), window.id() as u64).unwrap();
+		saver.keyboard(keyboard.status()).unwrap();
 
 		if throttle {
 			saver.throttle(true).unwrap();
@@ -111,7 +112,14 @@ impl Preview {
 						match event.response_type() {
 							// Handle keyboard events.
 							e if keyboard.owns_event(e) => {
-								keyboard.handle(&event);
+								if keyboard.handle(&event) {
+									let status = keyboard.status();
+
+									info!("keyboard: layout {}, caps lock {}, num lock {}, modifiers {:?}",
+										status.layout, status.caps, status.num, status.modifiers);
+
+									saver.keyboard(status).unwrap();
+								}
 							}
 
 							xcb::CONFIGURE_NOTIFY => {
@@ -18,6 +18,7 @@
 use std::io::{self, BufRead, BufReader, Write};
 use std::process::{self, Child, Command, Stdio};
 use std::ops::Deref;
+use std::collections::VecDeque;
 use std::thread;
 use std::sync::{Arc, Mutex};
 use std::sync::mpsc::{Receiver, TryRecvError, Sender, SendError, channel};
@@ -28,6 +29,11 @@ use api::{self, json};
 pub use api::{Safety, Password, Pointer};
 
 use error;
+use auth::Reason;
+use platform::keyboard;
+
+/// How many lines of the saver stderr are kept around.
+const STDERR: usize = 10;
 
 /// Interaction with an external process that implements the ScreenRuster IPC.
 ///
@@ -37,7 +43,9 @@ use error;
 /// When the process dies it sends a message signaling the death, otherwise it
 /// just forwards requests and responses.
 pub struct Saver {
+	name:     String,
 	process:  Arc<Mutex<Child>>,
+	stderr:   Arc<Mutex<VecDeque<String>>>,
 	receiver: Option<Receiver<Response>>,
 	sender:   Sender<Request>,
 
@@ -48,6 +56,13 @@ pub struct Saver {
 #[derive(Debug)]
 pub enum Request {
 	Forward(api::Request),
+	Timeout,
+	Delay(u64),
+	Prompt(String, bool),
+	Info(String),
+	Error(String),
+	Refused(Reason),
+	Keyboard(keyboard::Status),
 	Exit,
 }
 
@@ -228,6 +243,80 @@ impl Saver {
 							output.write_all(b"\n").unwrap();
 						}
 
+						Request::Timeout => {
+							output.write_all(json::stringify(object!{
+								"type"     => "password",
+								"password" => "timeout"
+							}).as_bytes()).unwrap();
+
+							output.write_all(b"\n").unwrap();
+						}
+
+						Request::Delay(seconds) => {
+							output.write_all(json::stringify(object!{
+								"type"  => "delay",
+								"delay" => seconds
+							}).as_bytes()).unwrap();
+
+							output.write_all(b"\n").unwrap();
+						}
+
+						Request::Prompt(message, echo) => {
+							output.write_all(json::stringify(object!{
+								"type"     => "password",
+								"password" => "prompt",
+								"message"  => message,
+								"echo"     => echo
+							}).as_bytes()).unwrap();
+
+							output.write_all(b"\n").unwrap();
+						}
+
+						Request::Info(message) => {
+							output.write_all(json::stringify(object!{
+								"type"     => "password",
+								"password" => "info",
+								"message"  => message
+							}).as_bytes()).unwrap();
+
+							output.write_all(b"\n").unwrap();
+						}
+
+						Request::Error(message) => {
+							output.write_all(json::stringify(object!{
+								"type"     => "password",
+								"password" => "error",
+								"message"  => message
+							}).as_bytes()).unwrap();
+
+							output.write_all(b"\n").unwrap();
+						}
+
+						Request::Refused(reason) => {
+							output.write_all(json::stringify(object!{
+								"type"     => "password",
+								"password" => "refused",
+								"reason"   => match reason {
+									Reason::AccountExpired  => "account-expired",
+									Reason::PasswordExpired => "password-expired",
+								}
+							}).as_bytes()).unwrap();
+
+							output.write_all(b"\n").unwrap();
+						}
+
+						Request::Keyboard(status) => {
+							output.write_all(json::stringify(object!{
+								"type"      => "keyboard",
+								"layout"    => status.layout,
+								"caps"      => status.caps,
+								"num"       => status.num,
+								"modifiers" => json::JsonValue::Array(status.modifiers.into_iter().map(|m| m.into()).collect())
+							}).as_bytes()).unwrap();
+
+							output.write_all(b"\n").unwrap();
+						}
+
 						Request::Exit => {
 							break;
 						}
@@ -236,25 +325,42 @@ impl Saver {
 			});
 		}
 
-		// Read from the process stderr and forward it to stderr.
+		// Read from the process stderr, keep the last lines around and forward it
+		// to stderr.
+		let stderr = Arc::new(Mutex::new(VecDeque::new()));
+
 		{
-			let input = child.lock().unwrap().stderr.take().unwrap();
+			let input  = child.lock().unwrap().stderr.take().unwrap();
+			let stderr = stderr.clone();
 
 			thread::spawn(move || {
 				for line in BufReader::new(input).lines() {
-					if line.is_err() {
-						break;
+					let line = if let Ok(line) = line {
+						line
 					}
+					else {
+						break;
+					};
 
 					if log_enabled!(log::LogLevel::Debug) {
-						writeln!(&mut io::stderr(), "{}", line.unwrap()).unwrap();
+						writeln!(&mut io::stderr(), "{}", line).unwrap();
+					}
+
+					let mut stderr = stderr.lock().unwrap();
+
+					if stderr.len() == STDERR {
+						stderr.pop_front();
 					}
+
+					stderr.push_back(line);
 				}
 			});
 		}
 
 		Ok(Saver {
+			name:     name.as_ref().into(),
 			process:  child,
+			stderr:   stderr,
 			receiver: Some(i_receiver),
 			sender:   i_sender,
 
@@ -263,6 +369,16 @@ impl Saver {
 		})
 	}
 
+	/// Get the name of the saver.
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	/// Get the last lines the saver wrote to stderr.
+	pub fn stderr(&self) -> Vec<String> {
+		self.stderr.lock().unwrap().iter().cloned().collect()
+	}
+
 	/// Check if the saver was requested to start.
 	pub fn was_started(&self) -> bool {
 		self.started
@@ -377,6 +493,41 @@ impl Saver {
 		self.send(api::Request::Password(password))
 	}
 
+	/// Tell the saver the authentication timed out.
+	pub fn timeout(&mut self) -> Result<(), SendError<Request>> {
+		self.sender.send(Request::Timeout)
+	}
+
+	/// Tell the saver how many seconds further attempts are refused for.
+	pub fn delay(&mut self, seconds: u64) -> Result<(), SendError<Request>> {
+		self.sender.send(Request::Delay(seconds))
+	}
+
+	/// Send a prompt for further input.
+	pub fn prompt<S: Into<String>>(&mut self, message: S, echo: bool) -> Result<(), SendError<Request>> {
+		self.sender.send(Request::Prompt(message.into(), echo))
+	}
+
+	/// Send an informational message from the authentication.
+	pub fn info<S: Into<String>>(&mut self, message: S) -> Result<(), SendError<Request>> {
+		self.sender.send(Request::Info(message.into()))
+	}
+
+	/// Send an error message from the authentication.
+	pub fn error<S: Into<String>>(&mut self, message: S) -> Result<(), SendError<Request>> {
+		self.sender.send(Request::Error(message.into()))
+	}
+
+	/// Tell the saver why the unlock was refused.
+	pub fn refused(&mut self, reason: Reason) -> Result<(), SendError<Request>> {
+		self.sender.send(Request::Refused(reason))
+	}
+
+	/// Tell the saver the keyboard status changed.
+	pub fn keyboard(&mut self, status: keyboard::Status) -> Result<(), SendError<Request>> {
+		self.sender.send(Request::Keyboard(status))
+	}
+
 	/// Start the saver.
 	pub fn start(&mut self) -> Result<(), SendError<Request>> {
 		self.started = true;
@@ -0,0 +1,321 @@
+// Copyleft (ↄ) meh. <meh@schizofreni.co> | http://meh.schizofreni.co
+//
+// This file is part of screenruster.
+//
+// screenruster is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// screenruster is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::str;
+
+use libc::{self, c_char, c_void, size_t};
+
+pub use wipe::wipe;
+
+/// The maximum size in bytes of a secret, including the terminating NUL.
+pub const CAPACITY: usize = 1024;
+
+/// A buffer for passwords and other sensitive input.
+///
+/// The buffer has a fixed capacity so it's never reallocated, leaving copies
+/// behind, it's locked in memory where possible so it never ends up in swap
+/// and it's zeroed when dropped.
+///
+/// The content never contains a NUL and is always followed by one, so it can
+/// be handed to C without further copies.
+pub struct Secret {
+	buffer: Box<[u8]>,
+	length: usize,
+}
+
+impl Secret {
+	pub fn new() -> Secret {
+		let buffer = vec![0u8; CAPACITY].into_boxed_slice();
+
+		unsafe {
+			if libc::mlock(buffer.as_ptr() as *const c_void, CAPACITY as size_t) != 0 {
+				debug!("secret: could not lock memory");
+			}
+		}
+
+		Secret {
+			buffer: buffer,
+			length: 0,
+		}
+	}
+
+	/// Get the length in bytes.
+	pub fn len(&self) -> usize {
+		self.length
+	}
+
+	/// Check if the secret is empty.
+	pub fn is_empty(&self) -> bool {
+		self.length == 0
+	}
+
+	/// Append a character, returns `false` if there's no space left or the
+	/// character is a NUL.
+	pub fn push(&mut self, ch: char) -> bool {
+		if ch == '\0' {
+			return false;
+		}
+
+		if self.length + ch.len_utf8() >= CAPACITY {
+			return false;
+		}
+
+		let mut encoded = [0u8; 4];
+		let     size    = encode(ch, &mut encoded);
+
+		self.buffer[self.length .. self.length + size].copy_from_slice(&encoded[.. size]);
+		self.length += size;
+
+		wipe(&mut encoded);
+
+		true
+	}
+
+	/// Remove the last character.
+	pub fn pop(&mut self) -> Option<char> {
+		let ch = if let Some(ch) = self.as_str().chars().rev().next() {
+			ch
+		}
+		else {
+			return None;
+		};
+
+		let length = self.length - ch.len_utf8();
+		wipe(&mut self.buffer[length .. self.length]);
+		self.length = length;
+
+		Some(ch)
+	}
+
+	/// Zero the content.
+	pub fn clear(&mut self) {
+		wipe(&mut self.buffer[.. self.length]);
+		self.length = 0;
+	}
+
+	/// Move everything after the given byte index into a new secret.
+	pub fn split_off(&mut self, at: usize) -> Secret {
+		assert!(self.as_str().is_char_boundary(at));
+
+		let mut other = Secret::new();
+		other.buffer[.. self.length - at].copy_from_slice(&self.buffer[at .. self.length]);
+		other.length = self.length - at;
+
+		wipe(&mut self.buffer[at .. self.length]);
+		self.length = at;
+
+		other
+	}
+
+	/// Compare in constant time over the whole capacity, so neither the content
+	/// nor its length leak.
+	pub fn matches(&self, other: &Secret) -> bool {
+		let difference = self.buffer.iter().zip(other.buffer.iter())
+			.fold(0, |acc, (a, b)| acc | (a ^ b));
+
+		(difference == 0) & (self.length == other.length)
+	}
+
+	/// Get the content.
+	pub fn as_str(&self) -> &str {
+		// Only whole characters are ever inserted.
+		unsafe {
+			str::from_utf8_unchecked(&self.buffer[.. self.length])
+		}
+	}
+
+	/// Get the content as a C string.
+	pub fn as_ptr(&self) -> *const c_char {
+		self.buffer.as_ptr() as *const c_char
+	}
+}
+
+/// Encode the character as UTF-8 in place, returning the used size.
+fn encode(ch: char, buffer: &mut [u8; 4]) -> usize {
+	let code = ch as u32;
+
+	match ch.len_utf8() {
+		1 => {
+			buffer[0] = code as u8;
+			1
+		}
+
+		2 => {
+			buffer[0] = 0xC0 | (code >> 6) as u8;
+			buffer[1] = 0x80 | (code & 0x3F) as u8;
+			2
+		}
+
+		3 => {
+			buffer[0] = 0xE0 | (code >> 12) as u8;
+			buffer[1] = 0x80 | ((code >> 6) & 0x3F) as u8;
+			buffer[2] = 0x80 | (code & 0x3F) as u8;
+			3
+		}
+
+		_ => {
+			buffer[0] = 0xF0 | (code >> 18) as u8;
+			buffer[1] = 0x80 | ((code >> 12) & 0x3F) as u8;
+			buffer[2] = 0x80 | ((code >> 6) & 0x3F) as u8;
+			buffer[3] = 0x80 | (code & 0x3F) as u8;
+			4
+		}
+	}
+}
+
+impl Default for Secret {
+	fn default() -> Secret {
+		Secret::new()
+	}
+}
+
+impl Clone for Secret {
+	fn clone(&self) -> Secret {
+		let mut other = Secret::new();
+		other.buffer[.. self.length].copy_from_slice(&self.buffer[.. self.length]);
+		other.length = self.length;
+
+		other
+	}
+}
+
+impl<'a> From<&'a str> for Secret {
+	fn from(value: &'a str) -> Secret {
+		let mut secret = Secret::new();
+
+		for ch in value.chars() {
+			if !secret.push(ch) {
+				break;
+			}
+		}
+
+		secret
+	}
+}
+
+impl fmt::Debug for Secret {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str("Secret(..)")
+	}
+}
+
+impl Drop for Secret {
+	fn drop(&mut self) {
+		wipe(&mut self.buffer);
+
+		unsafe {
+			libc::munlock(self.buffer.as_ptr() as *const c_void, CAPACITY as size_t);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Secret, CAPACITY, encode, wipe};
+
+	/// Check nothing is left past the content.
+	fn clean(secret: &Secret) -> bool {
+		secret.buffer[secret.length ..].iter().all(|&b| b == 0)
+	}
+
+	#[test]
+	fn utf8() {
+		for &ch in &['a', '\u{7f}', '\u{80}', 'é', '\u{7ff}', '\u{800}', '€', '\u{ffff}', '\u{10000}', '𝄞', '\u{10ffff}'] {
+			let mut buffer = [0u8; 4];
+			let     size   = encode(ch, &mut buffer);
+
+			assert_eq!(&buffer[.. size], ch.to_string().as_bytes());
+		}
+	}
+
+	#[test]
+	fn push() {
+		let mut secret = Secret::new();
+
+		for ch in "pässwörd€".chars() {
+			assert!(secret.push(ch));
+		}
+
+		assert!(!secret.push('\0'));
+		assert_eq!(secret.as_str(), "pässwörd€");
+		assert!(clean(&secret));
+	}
+
+	#[test]
+	fn capacity() {
+		let mut secret = Secret::new();
+
+		while secret.push('€') { }
+
+		assert!(secret.len() < CAPACITY);
+		assert!(secret.len() + '€'.len_utf8() >= CAPACITY);
+		assert!(clean(&secret));
+	}
+
+	#[test]
+	fn pop() {
+		let mut secret = Secret::from("pässwörd€");
+
+		assert_eq!(secret.pop(), Some('€'));
+		assert_eq!(secret.pop(), Some('d'));
+		assert_eq!(secret.as_str(), "pässwör");
+		assert!(clean(&secret));
+
+		while secret.pop().is_some() { }
+
+		assert!(secret.is_empty());
+		assert!(clean(&secret));
+	}
+
+	#[test]
+	fn clear() {
+		let mut secret = Secret::from("pässwörd");
+		secret.clear();
+
+		assert!(secret.is_empty());
+		assert!(clean(&secret));
+	}
+
+	#[test]
+	fn split_off() {
+		let mut secret = Secret::from("password123456");
+		let     code   = secret.split_off(8);
+
+		assert_eq!(secret.as_str(), "password");
+		assert_eq!(code.as_str(), "123456");
+		assert!(clean(&secret));
+		assert!(clean(&code));
+	}
+
+	#[test]
+	fn matches() {
+		assert!(Secret::from("password").matches(&Secret::from("password")));
+		assert!(!Secret::from("password").matches(&Secret::from("passwore")));
+		assert!(!Secret::from("password").matches(&Secret::from("password1")));
+		assert!(!Secret::from("").matches(&Secret::from("a")));
+		assert!(Secret::new().matches(&Secret::new()));
+	}
+
+	#[test]
+	fn wiped() {
+		let mut buffer = *b"password";
+		wipe(&mut buffer);
+
+		assert_eq!(&buffer, &[0u8; 8]);
+	}
+}
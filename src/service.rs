@@ -0,0 +1,28 @@
+// Copyleft (ↄ) meh. <meh@schizofreni.co> | http://meh.schizofreni.co
+//
+// This file is part of screenruster.
+//
+// screenruster is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// screenruster is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
+
+/// Check the PAM service is a plain name, so it can't point outside the PAM
+/// configuration directory.
+pub fn valid(service: &str) -> bool {
+	!service.is_empty() && !service.starts_with('.') && service.chars().all(|c| match c {
+		'a' ... 'z' | 'A' ... 'Z' | '0' ... '9' | '-' | '_' | '.' =>
+			true,
+
+		_ =>
+			false
+	})
+}
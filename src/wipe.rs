@@ -0,0 +1,30 @@
+// Copyleft (ↄ) meh. <meh@schizofreni.co> | http://meh.schizofreni.co
+//
+// This file is part of screenruster.
+//
+// screenruster is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// screenruster is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with screenruster.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Shared with the `screenruster-auth` helper, so it can't depend on anything
+//! else in the crate.
+
+use std::ptr;
+
+/// Zero the given memory making sure it's not optimized away.
+pub fn wipe(buffer: &mut [u8]) {
+	for byte in buffer.iter_mut() {
+		unsafe {
+			ptr::write_volatile(byte, 0);
+		}
+	}
+}